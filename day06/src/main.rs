@@ -1,4 +1,6 @@
+use std::collections::BTreeMap;
 use std::collections::HashSet;
+use std::fmt;
 use std::fs;
 
 fn main() {
@@ -9,6 +11,47 @@ fn main() {
     println!("Part 1: {}.", sum);
     let sum = count_common_answers(&groups);
     println!("Part 2: {}.", sum);
+
+    if std::env::args().any(|arg| arg == "--stats") {
+        print!("{}", summarize(&groups));
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct QuestionStats {
+    groups: usize,
+    individuals: usize,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Summary {
+    questions: BTreeMap<u8, QuestionStats>,
+    unanimous: Vec<usize>,
+    group_sizes: BTreeMap<usize, usize>,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Question  Groups  Individuals")?;
+        for (question, stats) in &self.questions {
+            writeln!(
+                f,
+                "{:>8}  {:>6}  {:>11}",
+                *question as char, stats.groups, stats.individuals
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Unanimous groups: {:?}", self.unanimous)?;
+
+        writeln!(f)?;
+        writeln!(f, "Group size  Count")?;
+        for (size, count) in &self.group_sizes {
+            writeln!(f, "{:>10}  {:>5}", size, count)?;
+        }
+
+        Ok(())
+    }
 }
 
 fn unique_answers(group: &str) -> HashSet<u8> {
@@ -40,6 +83,37 @@ fn count_common_answers(groups: &Vec<&str>) -> usize {
     })
 }
 
+fn summarize(groups: &Vec<&str>) -> Summary {
+    let mut summary = Summary::default();
+
+    for (i, group) in groups.iter().enumerate() {
+        let people: Vec<&str> = group.lines().filter(|l| !l.is_empty()).collect();
+
+        for question in unique_answers(group) {
+            let stats = summary.questions.entry(question).or_default();
+            stats.groups += 1;
+            stats.individuals += people
+                .iter()
+                .filter(|person| person.as_bytes().contains(&question))
+                .count();
+        }
+
+        let first = people.first().map(|p| p.bytes().collect::<HashSet<u8>>());
+        if let Some(first) = first {
+            if people
+                .iter()
+                .all(|person| person.bytes().collect::<HashSet<u8>>() == first)
+            {
+                summary.unanimous.push(i);
+            }
+        }
+
+        *summary.group_sizes.entry(people.len()).or_insert(0) += 1;
+    }
+
+    summary
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -57,4 +131,37 @@ mod tests {
 
         assert_eq!(count_common_answers(&groups), 6)
     }
+
+    #[test]
+    fn test_summarize() {
+        let groups = Vec::from(["abc", "a\nb\nc", "ab\nac", "a\na\na\na", "b"]);
+        let summary = summarize(&groups);
+
+        assert_eq!(
+            summary.questions[&b'a'],
+            QuestionStats {
+                groups: 4,
+                individuals: 8
+            }
+        );
+        assert_eq!(
+            summary.questions[&b'b'],
+            QuestionStats {
+                groups: 4,
+                individuals: 4
+            }
+        );
+        assert_eq!(
+            summary.questions[&b'c'],
+            QuestionStats {
+                groups: 3,
+                individuals: 3
+            }
+        );
+        assert_eq!(summary.unanimous, vec![0, 3, 4]);
+        assert_eq!(
+            summary.group_sizes,
+            [(1, 2), (2, 1), (3, 1), (4, 1)].iter().cloned().collect()
+        );
+    }
 }
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fmt;
 use std::fs;
+use std::str::FromStr;
 
 type Triplet = (String, u32, String);
 
@@ -16,98 +18,459 @@ fn main() -> Result<(), String> {
         .flatten()
         .collect();
 
-    println!("Part 1: {}.", count1(&triplets));
-    println!("Part 2: {}.", count2(&triplets));
+    let graph = BagGraph::new(&triplets);
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("--dot") {
+        let colour = args.get(3).map(String::as_str).unwrap_or("shiny gold");
+        let scope = match args.get(2).map(String::as_str) {
+            None | Some("all") => Scope::All,
+            Some("ancestors") => Scope::Ancestors(colour),
+            Some("descendants") => Scope::Descendants(colour),
+            Some(other) => return Err(format!("Invalid scope \"{}\".", other)),
+        };
+
+        print!("{}", graph.to_dot(scope, "shiny gold")?);
+        return Ok(());
+    }
+
+    println!("Part 1: {}.", graph.ancestors("shiny gold")?.len());
+    println!("Part 2: {}.", graph.total_contained("shiny gold")?);
 
     Ok(())
 }
 
 fn parse_line(line: &&str) -> Result<Vec<Triplet>, String> {
-    match line.splitn(2, " bags contain ").collect::<Vec<&str>>()[..] {
-        [_, "no other bags."] => Ok(Vec::new()),
-        [container, rest] => Ok(rest
-            .split(", ")
-            .map(|x| {
-                if let [n, a, b, _] = x.split(' ').collect::<Vec<_>>()[..] {
-                    if let Ok(amount) = n.parse::<u32>() {
-                        Ok((container.to_string(), amount, format!("{} {}", a, b)))
-                    } else {
-                        Err(format!(
-                            "Could not parse number of bags in line \"{}\".",
-                            line
-                        ))
-                    }
-                } else {
-                    Err(format!("Could not parse line \"{}\"", line))
+    Ok(line.parse::<Rule>()?.triplets())
+}
+
+#[derive(Debug, PartialEq)]
+struct Rule {
+    container: String,
+    contents: Vec<(u32, String)>,
+}
+
+impl Rule {
+    fn triplets(self) -> Vec<Triplet> {
+        let container = self.container;
+
+        self.contents
+            .into_iter()
+            .map(|(amount, contained)| (container.clone(), amount, contained))
+            .collect()
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+
+        let container = parser.colour()?;
+        parser.expect(&["bags", "bag"])?;
+        parser.expect(&["contain"])?;
+
+        let mut contents = Vec::new();
+
+        if parser.peek() == Some("no") {
+            parser.expect(&["no"])?;
+            parser.expect(&["other"])?;
+            parser.expect(&["bags", "bag"])?;
+        } else {
+            loop {
+                let amount = parser.amount()?;
+                let colour = parser.colour()?;
+                parser.expect(&["bags", "bag"])?;
+                contents.push((amount, colour));
+
+                if parser.peek() != Some(",") {
+                    break;
                 }
-            })
-            .collect::<Result<Vec<_>, String>>()?),
-        _ => Err(format!("Could not parse line \"{}\"", line)),
+                parser.expect(&[","])?;
+            }
+        }
+
+        parser.expect(&["."])?;
+        parser.end()?;
+
+        Ok(Self {
+            container,
+            contents,
+        })
     }
 }
 
-fn count1(triplets: &Vec<Triplet>) -> usize {
-    let mut map = HashMap::new();
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} bags contain ", self.container)?;
+
+        if self.contents.is_empty() {
+            return write!(f, "no other bags.");
+        }
+
+        for (i, (amount, colour)) in self.contents.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            let plural = if *amount == 1 { "" } else { "s" };
+            write!(f, "{} {} bag{}", amount, colour, plural)?;
+        }
 
-    for (value, _, key) in triplets {
-        let items = map.entry(key).or_insert(Vec::new());
-        items.push(value);
+        write!(f, ".")
     }
+}
 
-    let mut visited = HashSet::new();
-    let start = "shiny gold".to_string();
-    let mut to_visit = Vec::from([&start]);
+struct Parser<'a> {
+    line: &'a str,
+    tokens: Vec<(usize, &'a str)>,
+    pos: usize,
+}
 
-    loop {
-        to_visit = to_visit
-            .iter()
-            .flat_map(|k| map.remove(k).unwrap_or(Vec::new()))
-            .collect();
+impl<'a> Parser<'a> {
+    fn new(line: &'a str) -> Self {
+        let mut tokens = Vec::new();
+        let mut start = None;
+
+        for (i, chr) in line.char_indices() {
+            if chr.is_whitespace() || chr == ',' || chr == '.' {
+                if let Some(s) = start.take() {
+                    tokens.push((s, &line[s..i]));
+                }
+                if !chr.is_whitespace() {
+                    tokens.push((i, &line[i..i + 1]));
+                }
+            } else if start.is_none() {
+                start = Some(i);
+            }
+        }
+
+        if let Some(s) = start {
+            tokens.push((s, &line[s..]));
+        }
+
+        Self {
+            line,
+            tokens,
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|&(_, token)| token)
+    }
+
+    fn error(&self, expected: &str) -> String {
+        match self.tokens.get(self.pos) {
+            Some((col, token)) => format!(
+                "Expected {} but found \"{}\" at column {} in line \"{}\".",
+                expected,
+                token,
+                col + 1,
+                self.line
+            ),
+            None => format!(
+                "Expected {} but found end of line at column {} in line \"{}\".",
+                expected,
+                self.line.len() + 1,
+                self.line
+            ),
+        }
+    }
+
+    fn expect(&mut self, options: &[&str]) -> Result<(), String> {
+        match self.peek() {
+            Some(token) if options.contains(&token) => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.error(&format!("\"{}\"", options.join("\" or \"")))),
+        }
+    }
+
+    fn amount(&mut self) -> Result<u32, String> {
+        match self.peek().map(str::parse::<u32>) {
+            Some(Ok(amount)) => {
+                self.pos += 1;
+                Ok(amount)
+            }
+            _ => Err(self.error("a number of bags")),
+        }
+    }
+
+    fn colour(&mut self) -> Result<String, String> {
+        let mut words = Vec::new();
+
+        while let Some(token) = self.peek() {
+            if token == "bag"
+                || token == "bags"
+                || !token.chars().all(|c| c.is_alphabetic() || c == '-')
+            {
+                break;
+            }
+            words.push(token);
+            self.pos += 1;
+        }
 
-        if to_visit.is_empty() {
-            break;
+        if words.is_empty() {
+            return Err(self.error("a colour"));
         }
 
-        visited.extend(to_visit.iter().cloned());
+        Ok(words.join(" "))
+    }
+
+    fn end(&self) -> Result<(), String> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error("end of line")),
+        }
     }
+}
 
-    visited.len()
+struct BagGraph {
+    ids: HashMap<String, usize>,
+    names: Vec<String>,
+    contents: Vec<Vec<(u32, usize)>>,
+    containers: Vec<Vec<usize>>,
 }
 
-fn count2(triplets: &Vec<Triplet>) -> u32 {
-    let mut map = HashMap::new();
+impl BagGraph {
+    fn new(triplets: &[Triplet]) -> Self {
+        let mut graph = Self {
+            ids: HashMap::new(),
+            names: Vec::new(),
+            contents: Vec::new(),
+            containers: Vec::new(),
+        };
+
+        for (container, amount, contained) in triplets {
+            let container = graph.intern(container);
+            let contained = graph.intern(contained);
+            graph.contents[container].push((*amount, contained));
+            graph.containers[contained].push(container);
+        }
+
+        graph
+    }
+
+    fn intern(&mut self, colour: &str) -> usize {
+        if let Some(&id) = self.ids.get(colour) {
+            return id;
+        }
+
+        let id = self.names.len();
+        self.ids.insert(colour.to_string(), id);
+        self.names.push(colour.to_string());
+        self.contents.push(Vec::new());
+        self.containers.push(Vec::new());
+        id
+    }
+
+    fn id(&self, colour: &str) -> Result<usize, String> {
+        self.ids
+            .get(colour)
+            .cloned()
+            .ok_or_else(|| format!("Unknown bag colour \"{}\".", colour))
+    }
+
+    fn find_cycle(&self, start: usize) -> Option<Vec<&str>> {
+        let mut state = vec![Visit::New; self.names.len()];
 
-    for (container, amount, contained) in triplets {
-        let items = map.entry(container).or_insert(Vec::new());
-        items.push((*amount, contained));
+        self.visit(start, &mut state, &mut Vec::new())
+            .map(|cycle| cycle.iter().map(|&id| self.names[id].as_str()).collect())
     }
 
-    let start = "shiny gold".to_string();
-    let default = Vec::new();
-    let mut count = 0;
-    let mut to_visit = Vec::from([(1, &start)]);
+    fn visit(
+        &self,
+        id: usize,
+        state: &mut Vec<Visit>,
+        path: &mut Vec<usize>,
+    ) -> Option<Vec<usize>> {
+        match state[id] {
+            Visit::Done => return None,
+            Visit::Active => {
+                let start = path.iter().position(|&x| x == id).unwrap();
+                let mut cycle = path[start..].to_vec();
+                cycle.push(id);
+                return Some(cycle);
+            }
+            Visit::New => {}
+        }
+
+        state[id] = Visit::Active;
+        path.push(id);
 
-    loop {
-        to_visit = to_visit
+        for &(_, contained) in &self.contents[id] {
+            if let Some(cycle) = self.visit(contained, state, path) {
+                return Some(cycle);
+            }
+        }
+
+        path.pop();
+        state[id] = Visit::Done;
+        None
+    }
+
+    fn ancestor_ids(&self, id: usize) -> HashSet<usize> {
+        let mut visited = HashSet::new();
+        let mut to_visit = vec![id];
+
+        while let Some(id) = to_visit.pop() {
+            for &container in &self.containers[id] {
+                if visited.insert(container) {
+                    to_visit.push(container);
+                }
+            }
+        }
+
+        visited
+    }
+
+    fn descendant_ids(&self, id: usize) -> HashSet<usize> {
+        let mut visited = HashSet::new();
+        let mut to_visit = vec![id];
+
+        while let Some(id) = to_visit.pop() {
+            for &(_, contained) in &self.contents[id] {
+                if visited.insert(contained) {
+                    to_visit.push(contained);
+                }
+            }
+        }
+
+        visited
+    }
+
+    fn ancestors(&self, colour: &str) -> Result<HashSet<&str>, String> {
+        Ok(self
+            .ancestor_ids(self.id(colour)?)
+            .into_iter()
+            .map(|id| self.names[id].as_str())
+            .collect())
+    }
+
+    fn topological_order(&self, start: usize) -> Result<Vec<usize>, String> {
+        let mut reachable = self.descendant_ids(start);
+        reachable.insert(start);
+
+        let mut remaining = vec![0; self.names.len()];
+        for &id in &reachable {
+            for &(_, contained) in &self.contents[id] {
+                remaining[contained] += 1;
+            }
+        }
+
+        let mut ready: Vec<usize> = reachable
             .iter()
-            .flat_map(|(amt, container)| {
-                map.get(container)
-                    .unwrap_or(&default)
-                    .iter()
-                    .map(|(amt2, contained)| {
-                        let add = amt * amt2;
-                        count += add;
-
-                        (add, *contained)
-                    })
-                    .collect::<Vec<(u32, &String)>>()
-            })
+            .cloned()
+            .filter(|&id| remaining[id] == 0)
             .collect();
+        let mut order = Vec::with_capacity(reachable.len());
 
-        if to_visit.is_empty() {
-            return count;
+        while let Some(id) = ready.pop() {
+            order.push(id);
+
+            for &(_, contained) in &self.contents[id] {
+                remaining[contained] -= 1;
+                if remaining[contained] == 0 {
+                    ready.push(contained);
+                }
+            }
         }
+
+        if order.len() < reachable.len() {
+            let cycle = self.find_cycle(start).unwrap_or_default();
+            return Err(format!(
+                "Bag rules contain a cycle: {}.",
+                cycle.join(" -> ")
+            ));
+        }
+
+        Ok(order)
     }
+
+    fn total_contained(&self, colour: &str) -> Result<u128, String> {
+        let start = self.id(colour)?;
+        let mut totals = vec![0u128; self.names.len()];
+
+        for id in self.topological_order(start)?.into_iter().rev() {
+            totals[id] = self.contents[id]
+                .iter()
+                .try_fold(0u128, |acc, &(amount, contained)| {
+                    totals[contained]
+                        .checked_add(1)
+                        .and_then(|n| n.checked_mul(amount as u128))
+                        .and_then(|n| acc.checked_add(n))
+                })
+                .ok_or_else(|| {
+                    format!("Overflow counting the bags inside \"{}\".", self.names[id])
+                })?;
+        }
+
+        Ok(totals[start])
+    }
+
+    fn to_dot(&self, scope: Scope, highlight: &str) -> Result<String, String> {
+        let included: HashSet<usize> = match scope {
+            Scope::All => (0..self.names.len()).collect(),
+            Scope::Ancestors(colour) => {
+                let id = self.id(colour)?;
+                let mut ids = self.ancestor_ids(id);
+                ids.insert(id);
+                ids
+            }
+            Scope::Descendants(colour) => {
+                let id = self.id(colour)?;
+                let mut ids = self.descendant_ids(id);
+                ids.insert(id);
+                ids
+            }
+        };
+
+        let mut dot = String::from("digraph bags {\n");
+
+        for (id, name) in self.names.iter().enumerate() {
+            if !included.contains(&id) {
+                continue;
+            }
+
+            if name == highlight {
+                dot.push_str(&format!(
+                    "    \"{}\" [style=filled, fillcolor=gold];\n",
+                    name
+                ));
+            } else {
+                dot.push_str(&format!("    \"{}\";\n", name));
+            }
+        }
+
+        for (id, contents) in self.contents.iter().enumerate() {
+            for &(amount, contained) in contents {
+                if included.contains(&id) && included.contains(&contained) {
+                    dot.push_str(&format!(
+                        "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                        self.names[id], self.names[contained], amount
+                    ));
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+}
+
+enum Scope<'a> {
+    All,
+    Ancestors(&'a str),
+    Descendants(&'a str),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    New,
+    Active,
+    Done,
 }
 
 #[cfg(test)]
@@ -159,12 +522,190 @@ mod tests {
     }
 
     #[test]
-    fn test_count1() {
-        assert_eq!(count1(&triplets()), 4);
+    fn test_parse_rule() -> Result<(), String> {
+        assert_eq!(
+            "  pale  blue-green bags contain 1 gold bag ,3 very dark navy blue bag."
+                .parse::<Rule>()?,
+            Rule {
+                container: "pale blue-green".to_string(),
+                contents: vec![
+                    (1, "gold".to_string()),
+                    (3, "very dark navy blue".to_string())
+                ]
+            }
+        );
+
+        assert_eq!(
+            "light red bags contain 1 bright white, 2 muted yellow bags."
+                .parse::<Rule>()
+                .unwrap_err(),
+            "Expected \"bags\" or \"bag\" but found \",\" at column 38 in line \
+             \"light red bags contain 1 bright white, 2 muted yellow bags.\"."
+        );
+
+        assert_eq!(
+            "light red bags contain two bright white bags."
+                .parse::<Rule>()
+                .unwrap_err(),
+            "Expected a number of bags but found \"two\" at column 24 in line \
+             \"light red bags contain two bright white bags.\"."
+        );
+
+        assert_eq!(
+            "light red bags contain no other bags"
+                .parse::<Rule>()
+                .unwrap_err(),
+            "Expected \".\" but found end of line at column 37 in line \
+             \"light red bags contain no other bags\"."
+        );
+
+        Ok(())
     }
 
     #[test]
-    fn test_count2() {
-        assert_eq!(count2(&triplets()), 32);
+    fn test_display_rule() -> Result<(), String> {
+        for line in &[
+            "light red bags contain 1 bright white bag, 2 muted yellow bags.",
+            "faded blue bags contain no other bags.",
+            "pale blue-green bags contain 1 very dark navy blue bag.",
+        ] {
+            assert_eq!(line.parse::<Rule>()?.to_string(), *line);
+        }
+
+        assert_eq!(
+            "gold  bags contain 2 red bag , 1 blue bags ."
+                .parse::<Rule>()?
+                .to_string(),
+            "gold bags contain 2 red bags, 1 blue bag."
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ancestors() -> Result<(), String> {
+        let graph = BagGraph::new(&triplets());
+        assert_eq!(graph.ancestors("shiny gold")?.len(), 4);
+        assert_eq!(
+            graph.ancestors("muted yellow")?,
+            ["light red", "dark orange"].iter().cloned().collect()
+        );
+        assert!(graph.ancestors("light red")?.is_empty());
+        assert!(graph.ancestors("mauve").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_total_contained() -> Result<(), String> {
+        let graph = BagGraph::new(&triplets());
+        assert_eq!(graph.total_contained("shiny gold")?, 32);
+        assert_eq!(graph.total_contained("dark olive")?, 7);
+        assert_eq!(graph.total_contained("faded blue")?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_total_contained_deep() -> Result<(), String> {
+        let triplets: Vec<Triplet> = (0..40)
+            .map(|i| (format!("shade {}", i), 1000, format!("shade {}", i + 1)))
+            .chain((0..40).map(|i| (format!("shade {}", i), 1, format!("shade {}", i + 1))))
+            .collect();
+        let graph = BagGraph::new(&triplets);
+
+        assert_eq!(
+            graph.total_contained("shade 36")?,
+            1001 + 1001 * 1001 + 1001u128.pow(3) + 1001u128.pow(4)
+        );
+        assert!(graph.total_contained("shade 0").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_dot() -> Result<(), String> {
+        let graph = BagGraph::new(&triplets());
+
+        assert_eq!(
+            graph.to_dot(Scope::Ancestors("bright white"), "shiny gold")?,
+            "\
+digraph bags {
+    \"light red\";
+    \"bright white\";
+    \"dark orange\";
+    \"light red\" -> \"bright white\" [label=\"1\"];
+    \"dark orange\" -> \"bright white\" [label=\"3\"];
+}
+"
+        );
+
+        assert_eq!(
+            graph.to_dot(Scope::Descendants("dark olive"), "shiny gold")?,
+            "\
+digraph bags {
+    \"dark olive\";
+    \"faded blue\";
+    \"dotted black\";
+    \"dark olive\" -> \"faded blue\" [label=\"3\"];
+    \"dark olive\" -> \"dotted black\" [label=\"4\"];
+}
+"
+        );
+
+        let dot = graph.to_dot(Scope::All, "shiny gold")?;
+        assert!(dot.contains("    \"shiny gold\" [style=filled, fillcolor=gold];\n"));
+        assert_eq!(dot.matches(" -> ").count(), triplets().len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_cycle() -> Result<(), String> {
+        let graph = BagGraph::new(&triplets());
+        assert_eq!(graph.find_cycle(graph.id("light red")?), None);
+
+        let mut triplets = triplets();
+        triplets.push(("faded blue".to_string(), 1, "muted yellow".to_string()));
+        let graph = BagGraph::new(&triplets);
+
+        assert_eq!(
+            graph.find_cycle(graph.id("shiny gold")?),
+            Some(vec![
+                "shiny gold",
+                "dark olive",
+                "faded blue",
+                "muted yellow",
+                "shiny gold"
+            ])
+        );
+        assert_eq!(
+            graph.total_contained("light red"),
+            Err(
+                "Bag rules contain a cycle: shiny gold -> dark olive -> faded blue -> \
+                 muted yellow -> shiny gold."
+                    .to_string()
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unreachable_cycle() -> Result<(), String> {
+        let mut triplets = triplets();
+        triplets.push(("wavy red".to_string(), 2, "wavy blue".to_string()));
+        triplets.push(("wavy blue".to_string(), 1, "wavy red".to_string()));
+        triplets.push(("wavy blue".to_string(), 1, "shiny gold".to_string()));
+        let graph = BagGraph::new(&triplets);
+
+        assert_eq!(graph.total_contained("shiny gold")?, 32);
+        assert_eq!(graph.total_contained("dark olive")?, 7);
+        assert_eq!(
+            graph.total_contained("wavy red"),
+            Err("Bag rules contain a cycle: wavy red -> wavy blue -> wavy red.".to_string())
+        );
+
+        Ok(())
     }
 }
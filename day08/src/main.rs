@@ -1,56 +1,608 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
 use std::fs;
+use std::io::{self, BufRead, Write};
 use std::str::FromStr;
 
-#[derive(Clone, Debug, PartialEq)]
-enum Instr {
-    Nop(i32),
-    Acc(i32),
-    Jmp(i32),
+const REGISTERS: usize = 8;
+const ACC: usize = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operand {
+    Reg,
+    Imm,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Flow {
+    Next,
+    Jump(usize),
+    Branch(usize),
+}
+
+impl Flow {
+    fn target_operand(self) -> Option<usize> {
+        match self {
+            Flow::Next => None,
+            Flow::Jump(i) | Flow::Branch(i) => Some(i),
+        }
+    }
+}
+
+type Handler = fn(&mut Machine, &[i64]) -> Result<i64, String>;
+
+struct OpDef {
+    name: &'static str,
+    operands: &'static [Operand],
+    flow: Flow,
+    exec: Handler,
+}
+
+static NOP: OpDef = OpDef {
+    name: "nop",
+    operands: &[Operand::Imm],
+    flow: Flow::Next,
+    exec: |_, _| Ok(1),
+};
+
+static ACC_OP: OpDef = OpDef {
+    name: "acc",
+    operands: &[Operand::Imm],
+    flow: Flow::Next,
+    exec: |m, args| {
+        m.regs[ACC] = m.regs[ACC]
+            .checked_add(args[0])
+            .ok_or("Arithmetic overflow.")?;
+        Ok(1)
+    },
+};
+
+static JMP: OpDef = OpDef {
+    name: "jmp",
+    operands: &[Operand::Imm],
+    flow: Flow::Jump(0),
+    exec: |_, args| Ok(args[0]),
+};
+
+static ADD: OpDef = OpDef {
+    name: "add",
+    operands: &[Operand::Reg, Operand::Imm],
+    flow: Flow::Next,
+    exec: |m, args| {
+        let reg = &mut m.regs[args[0] as usize];
+        *reg = reg.checked_add(args[1]).ok_or("Arithmetic overflow.")?;
+        Ok(1)
+    },
+};
+
+static MUL: OpDef = OpDef {
+    name: "mul",
+    operands: &[Operand::Reg, Operand::Imm],
+    flow: Flow::Next,
+    exec: |m, args| {
+        let reg = &mut m.regs[args[0] as usize];
+        *reg = reg.checked_mul(args[1]).ok_or("Arithmetic overflow.")?;
+        Ok(1)
+    },
+};
+
+static JNZ: OpDef = OpDef {
+    name: "jnz",
+    operands: &[Operand::Reg, Operand::Imm],
+    flow: Flow::Branch(1),
+    exec: |m, args| {
+        if m.regs[args[0] as usize] != 0 {
+            Ok(args[1])
+        } else {
+            Ok(1)
+        }
+    },
+};
+
+static INP: OpDef = OpDef {
+    name: "inp",
+    operands: &[Operand::Reg],
+    flow: Flow::Next,
+    exec: |m, args| {
+        let value = m.input.pop_front().ok_or("No input available.")?;
+        m.regs[args[0] as usize] = value;
+        Ok(1)
+    },
+};
+
+static OUT: OpDef = OpDef {
+    name: "out",
+    operands: &[Operand::Reg],
+    flow: Flow::Next,
+    exec: |m, args| {
+        m.output.push(m.regs[args[0] as usize]);
+        Ok(1)
+    },
+};
+
+static OPCODES: [&OpDef; 8] = [&NOP, &ACC_OP, &JMP, &ADD, &MUL, &JNZ, &INP, &OUT];
+
+#[derive(Clone, Copy)]
+struct Instr {
+    op: &'static OpDef,
+    args: [i64; 2],
+}
+
+impl Instr {
+    fn new(op: &'static OpDef, args: &[i64]) -> Self {
+        let mut instr = Self { op, args: [0; 2] };
+        instr.args[..args.len()].copy_from_slice(args);
+        instr
+    }
+
+    fn args(&self) -> &[i64] {
+        &self.args[..self.op.operands.len()]
+    }
+
+    fn successors(&self) -> Vec<i64> {
+        match self.op.flow {
+            Flow::Next => vec![1],
+            Flow::Jump(i) => vec![self.args[i]],
+            Flow::Branch(i) => vec![1, self.args[i]],
+        }
+    }
+
+    fn flipped(&self) -> Option<Self> {
+        if std::ptr::eq(self.op, &NOP) {
+            Some(Self::new(&JMP, self.args()))
+        } else if std::ptr::eq(self.op, &JMP) {
+            Some(Self::new(&NOP, self.args()))
+        } else {
+            None
+        }
+    }
+}
+
+impl PartialEq for Instr {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.op, other.op) && self.args() == other.args()
+    }
+}
+
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.op.name)?;
+
+        for (operand, arg) in self.op.operands.iter().zip(self.args()) {
+            match operand {
+                Operand::Reg => write!(f, " {}", (b'a' + *arg as u8) as char)?,
+                Operand::Imm => write!(f, " {:+}", arg)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
 }
 
 impl FromStr for Instr {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let subs: Vec<&str> = s.splitn(2, ' ').collect();
-        let arg = subs[1]
-            .parse::<i32>()
-            .map_err(|_| "Could not parse integer argument.".to_string())?;
+        let mut tokens = s.split_whitespace();
+        let name = tokens.next().ok_or("Missing opcode.")?;
+        let op = OPCODES
+            .iter()
+            .find(|op| op.name == name)
+            .ok_or_else(|| format!("Invalid opcode \"{}\".", name))?;
+        let tokens: Vec<&str> = tokens.collect();
 
-        match subs[0] {
-            "nop" => Ok(Self::Nop(arg)),
-            "acc" => Ok(Self::Acc(arg)),
-            "jmp" => Ok(Self::Jmp(arg)),
-            other => Err(format!("Invalid opcode \"{}\".", other)),
+        if tokens.len() != op.operands.len() {
+            return Err(format!(
+                "Expected {} argument(s) for \"{}\", found {}.",
+                op.operands.len(),
+                name,
+                tokens.len()
+            ));
         }
+
+        let args = op
+            .operands
+            .iter()
+            .zip(tokens)
+            .map(|(operand, token)| match operand {
+                Operand::Reg => match token.as_bytes() {
+                    [r @ b'a'..=b'h'] => Ok((r - b'a') as i64),
+                    _ => Err(format!("Invalid register \"{}\".", token)),
+                },
+                Operand::Imm => token
+                    .parse::<i64>()
+                    .map_err(|_| "Could not parse integer argument.".to_string()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self::new(op, &args))
     }
 }
 
 type Prog = Vec<Instr>;
 
-struct ProgState<'a> {
-    map: HashMap<i32, &'a Instr>,
-    pc: i32,
-    acc: i32,
+fn strip_comment(line: &str) -> &str {
+    line.split(['#', ';']).next().unwrap_or("").trim()
 }
 
-impl<'a> From<&'a Prog> for ProgState<'a> {
-    fn from(prog: &'a Prog) -> Self {
+fn split_label(line: &str) -> Result<(Option<&str>, &str), String> {
+    match line.find(':') {
+        Some(i) => {
+            let label = line[..i].trim();
+            let valid = label.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+            if !valid {
+                return Err(format!("Invalid label \"{}\".", label));
+            }
+
+            Ok((Some(label), line[i + 1..].trim()))
+        }
+        None => Ok((None, line)),
+    }
+}
+
+fn assemble(source: &str) -> Result<Prog, String> {
+    let mut labels = HashMap::new();
+    let mut lines = Vec::new();
+
+    for (n, line) in source.lines().enumerate() {
+        let (label, rest) =
+            split_label(strip_comment(line)).map_err(|e| format!("Line {}: {}", n + 1, e))?;
+
+        if let Some(label) = label {
+            if labels.insert(label, lines.len() as i64).is_some() {
+                return Err(format!("Line {}: Duplicate label \"{}\".", n + 1, label));
+            }
+        }
+
+        if !rest.is_empty() {
+            lines.push((n + 1, rest));
+        }
+    }
+
+    lines
+        .iter()
+        .enumerate()
+        .map(|(pc, &(n, line))| {
+            let mut tokens: Vec<String> = line.split_whitespace().map(String::from).collect();
+            let target = OPCODES
+                .iter()
+                .find(|op| op.name == tokens[0])
+                .and_then(|op| op.flow.target_operand());
+
+            if let Some(token) = target.and_then(|i| tokens.get_mut(i + 1)) {
+                if token.parse::<i64>().is_err() {
+                    let address = labels
+                        .get(token.as_str())
+                        .ok_or_else(|| format!("Line {}: Unknown label \"{}\".", n, token))?;
+                    *token = format!("{:+}", address - pc as i64);
+                }
+            }
+
+            tokens
+                .join(" ")
+                .parse::<Instr>()
+                .map_err(|e| format!("Line {}: {}", n, e))
+        })
+        .collect()
+}
+
+fn disassemble(prog: &[Instr]) -> String {
+    let mut targets: Vec<usize> = prog
+        .iter()
+        .enumerate()
+        .filter_map(|(pc, instr)| {
+            let target = pc as i64 + instr.args[instr.op.flow.target_operand()?];
+            if target >= 0 && target <= prog.len() as i64 {
+                Some(target as usize)
+            } else {
+                None
+            }
+        })
+        .collect();
+    targets.sort_unstable();
+    targets.dedup();
+
+    let label = |address: usize| {
+        targets
+            .binary_search(&address)
+            .ok()
+            .map(|i| format!("L{}", i))
+    };
+    let mut output = String::new();
+
+    for (pc, instr) in prog.iter().enumerate() {
+        if let Some(label) = label(pc) {
+            output.push_str(&format!("{}:\n", label));
+        }
+
+        let mut line = instr.to_string();
+
+        if let Some(i) = instr.op.flow.target_operand() {
+            let target = pc as i64 + instr.args[i];
+            if let Some(label) = usize::try_from(target).ok().and_then(label) {
+                let mut tokens: Vec<String> = line.split(' ').map(String::from).collect();
+                tokens[i + 1] = label;
+                line = tokens.join(" ");
+            }
+        }
+
+        output.push_str(&format!("    {}\n", line));
+    }
+
+    if let Some(label) = label(prog.len()) {
+        output.push_str(&format!("{}:\n", label));
+    }
+
+    output
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Exit {
+    Terminated,
+    Loop(usize),
+    OutOfBounds(i64),
+    Fault(usize, String),
+}
+
+struct Machine<'a> {
+    prog: &'a [Instr],
+    pc: i64,
+    regs: [i64; REGISTERS],
+    visited: Vec<bool>,
+    halt_on_revisit: bool,
+    input: VecDeque<i64>,
+    output: Vec<i64>,
+}
+
+impl<'a> Machine<'a> {
+    fn new(prog: &'a [Instr]) -> Self {
         Self {
+            prog,
             pc: 0,
-            acc: 0,
-            map: (0i32..).zip(prog).collect(),
+            regs: [0; REGISTERS],
+            visited: vec![false; prog.len()],
+            halt_on_revisit: true,
+            input: VecDeque::new(),
+            output: Vec::new(),
+        }
+    }
+
+    fn acc(&self) -> i64 {
+        self.regs[ACC]
+    }
+
+    fn step(&mut self) -> Option<Exit> {
+        if self.pc == self.prog.len() as i64 {
+            return Some(Exit::Terminated);
+        } else if self.pc < 0 || self.pc > self.prog.len() as i64 {
+            return Some(Exit::OutOfBounds(self.pc));
+        }
+
+        let pc = self.pc as usize;
+
+        if self.halt_on_revisit && self.visited[pc] {
+            return Some(Exit::Loop(pc));
+        }
+
+        let instr = self.prog[pc];
+
+        let result = (instr.op.exec)(self, instr.args()).and_then(|offset| {
+            self.pc
+                .checked_add(offset)
+                .ok_or_else(|| "Arithmetic overflow.".to_string())
+        });
+
+        match result {
+            Ok(next) => {
+                self.visited[pc] = true;
+                self.pc = next;
+                None
+            }
+            Err(err) => Some(Exit::Fault(pc, err)),
+        }
+    }
+
+    fn run(&mut self) -> Exit {
+        loop {
+            if let Some(exit) = self.step() {
+                return exit;
+            }
         }
     }
 }
 
+struct Debugger<'a> {
+    machine: Machine<'a>,
+    breakpoints: HashSet<usize>,
+    watches: Vec<usize>,
+    trace: bool,
+    history: Vec<usize>,
+    exited: bool,
+    paused_at: Option<usize>,
+}
+
+impl<'a> Debugger<'a> {
+    fn new(prog: &'a [Instr]) -> Self {
+        Self {
+            machine: Machine::new(prog),
+            breakpoints: HashSet::new(),
+            watches: Vec::new(),
+            trace: false,
+            history: Vec::new(),
+            exited: false,
+            paused_at: None,
+        }
+    }
+
+    fn step(&mut self, out: &mut impl Write) -> io::Result<Option<Exit>> {
+        let pc = self.machine.pc;
+        let regs = self.machine.regs;
+        self.paused_at = None;
+
+        if let Some(exit) = self.machine.step() {
+            if self.exited {
+                writeln!(out, "Program has exited.")?;
+            } else {
+                self.report(&exit, out)?;
+                self.exited = true;
+            }
+            return Ok(Some(exit));
+        }
+
+        let pc = pc as usize;
+        self.history.push(pc);
+
+        if self.trace {
+            writeln!(
+                out,
+                "{:>5}  {:<12} acc={}",
+                pc,
+                self.machine.prog[pc].to_string(),
+                self.machine.acc()
+            )?;
+        }
+
+        for &reg in &self.watches {
+            if regs[reg] != self.machine.regs[reg] {
+                writeln!(
+                    out,
+                    "{}: {} -> {} at {}",
+                    (b'a' + reg as u8) as char,
+                    regs[reg],
+                    self.machine.regs[reg],
+                    pc
+                )?;
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn cont(&mut self, out: &mut impl Write) -> io::Result<Option<Exit>> {
+        loop {
+            let pc = self.machine.pc;
+
+            // Resuming from a breakpoint must not stop on it again.
+            if pc >= 0
+                && self.breakpoints.contains(&(pc as usize))
+                && self.paused_at != Some(pc as usize)
+            {
+                writeln!(out, "Breakpoint at {}.", pc)?;
+                self.paused_at = Some(pc as usize);
+                return Ok(None);
+            }
+
+            if let Some(exit) = self.step(out)? {
+                return Ok(Some(exit));
+            }
+        }
+    }
+
+    fn report(&self, exit: &Exit, out: &mut impl Write) -> io::Result<()> {
+        match exit {
+            Exit::Terminated => writeln!(out, "Terminated with acc={}.", self.machine.acc()),
+            Exit::OutOfBounds(pc) => writeln!(out, "Jumped out of bounds to {}.", pc),
+            Exit::Fault(pc, err) => writeln!(out, "Fault at {}: {}", pc, err),
+            Exit::Loop(pc) => {
+                writeln!(out, "Loop detected at {}:", pc)?;
+                let start = self.history.iter().position(|x| x == pc).unwrap_or(0);
+
+                for &pc in &self.history[start..] {
+                    writeln!(out, "{:>5}  {}", pc, self.machine.prog[pc])?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn run_script(&mut self, input: impl BufRead, out: &mut impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            let words: Vec<&str> = line.split_whitespace().collect();
+
+            match words[..] {
+                [] => {}
+                ["b", pc] | ["break", pc] => match pc.parse() {
+                    Ok(pc) => {
+                        self.breakpoints.insert(pc);
+                    }
+                    Err(_) => writeln!(out, "Invalid address \"{}\".", pc)?,
+                },
+                ["d", pc] | ["delete", pc] => match pc.parse() {
+                    Ok(pc) => {
+                        self.breakpoints.remove(&pc);
+                    }
+                    Err(_) => writeln!(out, "Invalid address \"{}\".", pc)?,
+                },
+                ["s"] | ["step"] => {
+                    self.step(out)?;
+                }
+                ["s", n] | ["step", n] => match n.parse::<usize>() {
+                    Ok(n) => {
+                        for _ in 0..n {
+                            if self.step(out)?.is_some() {
+                                break;
+                            }
+                        }
+                    }
+                    Err(_) => writeln!(out, "Invalid count \"{}\".", n)?,
+                },
+                ["c"] | ["continue"] => {
+                    self.cont(out)?;
+                }
+                ["w", reg] | ["watch", reg] => match reg.as_bytes() {
+                    [r @ b'a'..=b'h'] => self.watches.push((r - b'a') as usize),
+                    _ => writeln!(out, "Invalid register \"{}\".", reg)?,
+                },
+                ["t"] | ["trace"] => self.trace = !self.trace,
+                ["r"] | ["regs"] => {
+                    write!(out, "pc={}", self.machine.pc)?;
+                    for (i, value) in self.machine.regs.iter().enumerate() {
+                        write!(out, " {}={}", (b'a' + i as u8) as char, value)?;
+                    }
+                    writeln!(out)?;
+                }
+                ["q"] | ["quit"] => break,
+                _ => writeln!(out, "Unknown command \"{}\".", line)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
 fn main() -> Result<(), String> {
     let contents = fs::read_to_string("input.txt").expect("Could not read file.");
-    let prog: Prog = contents
-        .lines()
-        .map(|l| l.parse::<Instr>())
-        .collect::<Result<_, _>>()?;
+    let prog = assemble(&contents)?;
+
+    if std::env::args().any(|arg| arg == "--analyze") {
+        print!("{}", analyze(&prog));
+        return Ok(());
+    }
+
+    if std::env::args().any(|arg| arg == "--disassemble") {
+        print!("{}", disassemble(&prog));
+        return Ok(());
+    }
+
+    if std::env::args().any(|arg| arg == "--debug") {
+        let mut debugger = Debugger::new(&prog);
+        return debugger
+            .run_script(io::stdin().lock(), &mut io::stdout())
+            .map_err(|e| e.to_string());
+    }
 
     let res = run_prog(&prog);
     println!("Part 1: {}.", res);
@@ -60,57 +612,295 @@ fn main() -> Result<(), String> {
     Ok(())
 }
 
-fn step(state: &mut ProgState, instr: &Instr) {
-    match instr {
-        Instr::Nop(_) => state.pc += 1,
-        Instr::Acc(arg) => {
-            state.acc += arg;
-            state.pc += 1
+fn run_prog(prog: &[Instr]) -> i64 {
+    let mut machine = Machine::new(prog);
+    machine.run();
+    machine.acc()
+}
+
+fn run_prog2(prog: &[Instr]) -> Option<i64> {
+    let mut machine = Machine::new(prog);
+
+    match machine.run() {
+        Exit::Terminated => Some(machine.acc()),
+        _ => None,
+    }
+}
+
+fn reaches_end(prog: &[Instr]) -> Vec<bool> {
+    reaches_any(prog, &[prog.len()])
+}
+
+/// Marks every pc from which one of `exits` can be reached.
+fn reaches_any(prog: &[Instr], exits: &[usize]) -> Vec<bool> {
+    let len = prog.len() as i64;
+    let mut predecessors = vec![Vec::new(); prog.len() + 1];
+
+    for (i, instr) in prog.iter().enumerate() {
+        for offset in instr.successors() {
+            let target = i as i64 + offset;
+            if target >= 0 && target <= len {
+                predecessors[target as usize].push(i);
+            }
         }
-        Instr::Jmp(arg) => state.pc += arg,
-    };
+    }
+
+    let mut reaches = vec![false; prog.len() + 1];
+    let mut to_visit = exits.to_vec();
+    for &exit in exits {
+        reaches[exit] = true;
+    }
+
+    while let Some(i) = to_visit.pop() {
+        for &pred in &predecessors[i] {
+            if !reaches[pred] {
+                reaches[pred] = true;
+                to_visit.push(pred);
+            }
+        }
+    }
+
+    reaches
 }
 
-fn run_prog(prog: &Prog) -> i32 {
-    let mut state = ProgState::from(prog);
+fn repairs(prog: &[Instr]) -> Vec<usize> {
+    let reaches = reaches_end(prog);
+    let mut machine = Machine::new(prog);
 
-    while let Some(instr) = state.map.remove(&state.pc) {
-        step(&mut state, &instr);
+    if machine.run() == Exit::Terminated {
+        return Vec::new();
     }
 
-    state.acc
+    (0..prog.len())
+        .filter(|&i| machine.visited[i])
+        .filter(|&i| {
+            prog[i].flipped().is_some_and(|instr| {
+                instr.successors().iter().all(|offset| {
+                    let target = i as i64 + offset;
+                    target >= 0 && target <= prog.len() as i64 && reaches[target as usize]
+                })
+            })
+        })
+        .collect()
 }
 
-fn run_prog2(prog: &Prog) -> Option<i32> {
-    let mut state = ProgState::from(prog);
+fn try_run(prog: &[Instr]) -> Option<i64> {
+    let i = *repairs(prog).first()?;
+    let mut copy = prog.to_vec();
+    copy[i] = prog[i].flipped()?;
 
-    loop {
-        if state.pc as usize == prog.len() {
-            return Some(state.acc);
-        } else if let Some(instr) = state.map.remove(&state.pc) {
-            step(&mut state, &instr);
-        } else {
-            return None;
+    run_prog2(&copy)
+}
+
+#[derive(Debug, PartialEq)]
+struct Block {
+    start: usize,
+    end: usize,
+    successors: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Analysis {
+    blocks: Vec<Block>,
+    unreachable: Vec<usize>,
+    out_of_bounds: Vec<(usize, i64)>,
+    loops: Vec<Vec<usize>>,
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Basic blocks:")?;
+        for (i, block) in self.blocks.iter().enumerate() {
+            let successors: Vec<String> = block
+                .successors
+                .iter()
+                .map(|&s| match s {
+                    s if s == self.blocks.len() => "end".to_string(),
+                    s => s.to_string(),
+                })
+                .collect();
+            writeln!(
+                f,
+                "{:>5}  {}..{} -> {}",
+                i,
+                block.start,
+                block.end,
+                successors.join(", ")
+            )?;
+        }
+
+        writeln!(f, "Unreachable instructions: {:?}", self.unreachable)?;
+
+        for (pc, target) in &self.out_of_bounds {
+            writeln!(f, "Jump out of bounds at {} to {}.", pc, target)?;
         }
+
+        for blocks in &self.loops {
+            writeln!(f, "Infinite loop through blocks {:?}.", blocks)?;
+        }
+
+        Ok(())
     }
 }
 
-fn try_run(prog: &Prog) -> Option<i32> {
-    for i in 0..prog.len() {
-        let mut copy = prog.to_vec();
+fn analyze(prog: &[Instr]) -> Analysis {
+    let len = prog.len();
+    let mut leaders = vec![false; len + 1];
+    let mut out_of_bounds = Vec::new();
+
+    if len > 0 {
+        leaders[0] = true;
+    }
+
+    for (pc, instr) in prog.iter().enumerate() {
+        if instr.op.flow == Flow::Next {
+            continue;
+        }
+
+        leaders[pc + 1] = true;
+
+        for offset in instr.successors() {
+            let target = pc as i64 + offset;
+            if target >= 0 && target <= len as i64 {
+                leaders[target as usize] = true;
+            } else {
+                out_of_bounds.push((pc, target));
+            }
+        }
+    }
+
+    let starts: Vec<usize> = (0..len).filter(|&pc| leaders[pc]).collect();
+    let block_of = |pc: usize| match starts.binary_search(&pc) {
+        Ok(i) => i,
+        Err(_) => starts.len(),
+    };
+
+    let blocks: Vec<Block> = starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).cloned().unwrap_or(len);
+            let mut successors: Vec<usize> = prog[end - 1]
+                .successors()
+                .iter()
+                .map(|offset| (end - 1) as i64 + offset)
+                .filter(|&target| target >= 0 && target <= len as i64)
+                .map(|target| block_of(target as usize))
+                .collect();
+            successors.dedup();
+
+            Block {
+                start,
+                end,
+                successors,
+            }
+        })
+        .collect();
+
+    let mut reachable = vec![false; blocks.len() + 1];
+    let mut to_visit = vec![0];
+
+    while let Some(i) = to_visit.pop() {
+        if i < blocks.len() && !reachable[i] {
+            reachable[i] = true;
+            to_visit.extend(&blocks[i].successors);
+        }
+    }
+
+    let unreachable = blocks
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| !reachable[i])
+        .flat_map(|(_, block)| block.start..block.end)
+        .collect();
+
+    // Jumping out of bounds also leaves the program, so cycles with such an
+    // exit are not infinite.
+    let exits: Vec<usize> = out_of_bounds
+        .iter()
+        .map(|&(pc, _)| pc)
+        .chain(Some(len))
+        .collect();
+    let reaches = reaches_any(prog, &exits);
+    let loops = strongly_connected(&blocks)
+        .into_iter()
+        .filter(|component| {
+            let block = component[0];
+            reachable[block]
+                && !reaches[blocks[block].start]
+                && (component.len() > 1 || blocks[block].successors.contains(&block))
+        })
+        .collect();
+
+    Analysis {
+        blocks,
+        unreachable,
+        out_of_bounds,
+        loops,
+    }
+}
+
+fn strongly_connected(blocks: &[Block]) -> Vec<Vec<usize>> {
+    struct State {
+        index: Vec<Option<usize>>,
+        low: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<usize>,
+        next: usize,
+        components: Vec<Vec<usize>>,
+    }
+
+    fn visit(v: usize, blocks: &[Block], state: &mut State) {
+        state.index[v] = Some(state.next);
+        state.low[v] = state.next;
+        state.next += 1;
+        state.stack.push(v);
+        state.on_stack[v] = true;
+
+        for &w in blocks[v].successors.iter().filter(|&&w| w < blocks.len()) {
+            match state.index[w] {
+                None => {
+                    visit(w, blocks, state);
+                    state.low[v] = state.low[v].min(state.low[w]);
+                }
+                Some(index) if state.on_stack[w] => state.low[v] = state.low[v].min(index),
+                Some(_) => {}
+            }
+        }
+
+        if Some(state.low[v]) == state.index[v] {
+            let mut component = Vec::new();
 
-        match prog[i] {
-            Instr::Nop(arg) => copy[i] = Instr::Jmp(arg),
-            Instr::Jmp(arg) => copy[i] = Instr::Nop(arg),
-            _ => continue,
+            while let Some(w) = state.stack.pop() {
+                state.on_stack[w] = false;
+                component.push(w);
+                if w == v {
+                    break;
+                }
+            }
+
+            component.sort_unstable();
+            state.components.push(component);
         }
+    }
 
-        if let Some(res) = run_prog2(&copy) {
-            return Some(res);
+    let mut state = State {
+        index: vec![None; blocks.len()],
+        low: vec![0; blocks.len()],
+        on_stack: vec![false; blocks.len()],
+        stack: Vec::new(),
+        next: 0,
+        components: Vec::new(),
+    };
+
+    for v in 0..blocks.len() {
+        if state.index[v].is_none() {
+            visit(v, blocks, &mut state);
         }
     }
 
-    None
+    state.components.sort();
+    state.components
 }
 
 #[cfg(test)]
@@ -119,26 +909,98 @@ mod tests {
 
     #[test]
     fn test_parse_instr() -> Result<(), String> {
-        assert_eq!("nop +13".parse::<Instr>()?, Instr::Nop(13));
-        assert_eq!("acc +13".parse::<Instr>()?, Instr::Acc(13));
-        assert_eq!("acc -13".parse::<Instr>()?, Instr::Acc(-13));
-        assert_eq!("jmp -13".parse::<Instr>()?, Instr::Jmp(-13));
+        assert_eq!("nop +13".parse::<Instr>()?, Instr::new(&NOP, &[13]));
+        assert_eq!("acc +13".parse::<Instr>()?, Instr::new(&ACC_OP, &[13]));
+        assert_eq!("acc -13".parse::<Instr>()?, Instr::new(&ACC_OP, &[-13]));
+        assert_eq!("jmp -13".parse::<Instr>()?, Instr::new(&JMP, &[-13]));
+        assert_eq!("jnz c -2".parse::<Instr>()?, Instr::new(&JNZ, &[2, -2]));
+        assert_eq!("out h".parse::<Instr>()?, Instr::new(&OUT, &[7]));
+
+        assert!("nop".parse::<Instr>().is_err());
+        assert!("mul z +2".parse::<Instr>().is_err());
+        assert!("hcf +0".parse::<Instr>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_assemble() -> Result<(), String> {
+        let source = "\
+# Count down from the input.
+        inp b
+loop:   jnz b body   ; keep going while b is non-zero
+        jmp end
+
+body:   out b
+        add b -1
+        jmp loop
+end:
+";
+        let expected: Prog = "inp b\njnz b +2\njmp +4\nout b\nadd b -1\njmp -4"
+            .lines()
+            .map(|l| l.parse())
+            .collect::<Result<_, _>>()?;
+        assert_eq!(assemble(source)?, expected);
+
+        assert_eq!(
+            assemble("nop +0\njmp nowhere").unwrap_err(),
+            "Line 2: Unknown label \"nowhere\"."
+        );
+        assert_eq!(
+            assemble("a: nop +0\na: nop +0").unwrap_err(),
+            "Line 2: Duplicate label \"a\"."
+        );
+        assert_eq!(
+            assemble("nop +0\n\nnop").unwrap_err(),
+            "Line 3: Expected 1 argument(s) for \"nop\", found 0."
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_disassemble() -> Result<(), String> {
+        let prog = get_prog();
+        let listing = disassemble(&prog);
+
+        assert_eq!(
+            listing,
+            "    nop +0
+L0:
+    acc +1
+    jmp L2
+L1:
+    acc +3
+    jmp L0
+    acc -99
+L2:
+    acc +1
+    jmp L1
+    acc +6
+"
+        );
+        assert_eq!(assemble(&listing)?, prog);
+
+        let prog = assemble("jmp +1")?;
+        assert_eq!(disassemble(&prog), "    jmp L0\nL0:\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_instr() -> Result<(), String> {
+        for line in &["nop +0", "acc -99", "mul b +3", "inp a"] {
+            assert_eq!(line.parse::<Instr>()?.to_string(), *line);
+        }
 
         Ok(())
     }
 
     fn get_prog() -> Prog {
-        Vec::from([
-            Instr::Nop(0),
-            Instr::Acc(1),
-            Instr::Jmp(4),
-            Instr::Acc(3),
-            Instr::Jmp(-3),
-            Instr::Acc(-99),
-            Instr::Acc(1),
-            Instr::Jmp(-4),
-            Instr::Acc(6),
-        ])
+        "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6"
+            .lines()
+            .map(|l| l.parse().unwrap())
+            .collect()
     }
 
     #[test]
@@ -150,12 +1012,180 @@ mod tests {
     fn test_run_prog2() {
         let mut prog = get_prog();
         assert_eq!(run_prog2(&prog), None);
-        prog[7] = Instr::Nop(2);
+        prog[7] = Instr::new(&NOP, &[2]);
         assert_eq!(run_prog2(&prog), Some(8));
     }
 
+    #[test]
+    fn test_repairs() {
+        assert_eq!(repairs(&get_prog()), vec![7]);
+
+        let prog: Prog = "jmp +2\nnop +3\nnop +2\njmp -2\nacc +1"
+            .lines()
+            .map(|l| l.parse().unwrap())
+            .collect();
+        assert_eq!(repairs(&prog), vec![1, 2, 3]);
+
+        let mut prog = get_prog();
+        prog[7] = Instr::new(&NOP, &[-4]);
+        assert_eq!(repairs(&prog), vec![]);
+    }
+
     #[test]
     fn test_try_run() {
         assert_eq!(try_run(&get_prog()), Some(8));
     }
+
+    #[test]
+    fn test_machine() -> Result<(), String> {
+        // Count down from the input to one.
+        let prog: Prog = "inp b\njnz b +2\njmp +4\nout b\nadd b -1\njmp -4"
+            .lines()
+            .map(|l| l.parse())
+            .collect::<Result<_, _>>()?;
+
+        let mut machine = Machine::new(&prog);
+        machine.input.push_back(3);
+        assert_eq!(machine.run(), Exit::Loop(1));
+        assert_eq!(machine.output, vec![3]);
+
+        let mut machine = Machine::new(&prog);
+        machine.input.push_back(3);
+        machine.halt_on_revisit = false;
+        assert_eq!(machine.run(), Exit::Terminated);
+        assert_eq!(machine.output, vec![3, 2, 1]);
+
+        let mut machine = Machine::new(&prog);
+        assert_eq!(
+            machine.run(),
+            Exit::Fault(0, "No input available.".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_overflow() -> Result<(), String> {
+        let prog = assemble("add a +2\nl: mul a +1000000\njmp l")?;
+        let mut machine = Machine::new(&prog);
+        machine.halt_on_revisit = false;
+        assert_eq!(
+            machine.run(),
+            Exit::Fault(1, "Arithmetic overflow.".to_string())
+        );
+
+        let prog = assemble("acc +9223372036854775807\nacc +1")?;
+        let mut machine = Machine::new(&prog);
+        assert_eq!(
+            machine.run(),
+            Exit::Fault(1, "Arithmetic overflow.".to_string())
+        );
+        assert_eq!(machine.acc(), i64::MAX);
+        assert_eq!(run_prog(&prog), i64::MAX);
+
+        let prog = assemble("nop +0\njmp +9223372036854775807")?;
+        assert_eq!(
+            Machine::new(&prog).run(),
+            Exit::Fault(1, "Arithmetic overflow.".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_debugger() -> io::Result<()> {
+        let prog = get_prog();
+        let mut debugger = Debugger::new(&prog);
+        let mut out = Vec::new();
+        let script = "break 4\nwatch a\ncontinue\nregs\ntrace\nstep 2\ncontinue\n";
+
+        debugger.run_script(script.as_bytes(), &mut out)?;
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+a: 0 -> 1 at 1
+a: 1 -> 2 at 6
+a: 2 -> 5 at 3
+Breakpoint at 4.
+pc=4 a=5 b=0 c=0 d=0 e=0 f=0 g=0 h=0
+    4  jmp -3       acc=5
+Loop detected at 1:
+    1  acc +1
+    2  jmp +4
+    6  acc +1
+    7  jmp -4
+    3  acc +3
+    4  jmp -3
+Program has exited.
+"
+        );
+
+        let prog = assemble("acc +1\nacc +2\nacc +3").unwrap();
+        let mut debugger = Debugger::new(&prog);
+        let mut out = Vec::new();
+        let script = "break 0\nbreak 2\ncontinue\nregs\ncontinue\ncontinue\n";
+
+        debugger.run_script(script.as_bytes(), &mut out)?;
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+Breakpoint at 0.
+pc=0 a=0 b=0 c=0 d=0 e=0 f=0 g=0 h=0
+Breakpoint at 2.
+Terminated with acc=6.
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze() -> Result<(), String> {
+        let analysis = analyze(&get_prog());
+
+        assert_eq!(
+            analysis
+                .blocks
+                .iter()
+                .map(|b| (b.start, b.end, b.successors.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, 1, vec![1]),
+                (1, 3, vec![4]),
+                (3, 5, vec![1]),
+                (5, 6, vec![4]),
+                (6, 8, vec![2]),
+                (8, 9, vec![6]),
+            ]
+        );
+        assert_eq!(analysis.unreachable, vec![5, 8]);
+        assert!(analysis.out_of_bounds.is_empty());
+        assert_eq!(analysis.loops, vec![vec![1, 2, 4]]);
+
+        let prog = assemble(
+            "\
+start:  jnz a skip
+        jmp +10
+        acc +2
+skip:   add a -1
+        jnz a start",
+        )?;
+        let analysis = analyze(&prog);
+
+        assert_eq!(analysis.unreachable, vec![2]);
+        assert_eq!(analysis.out_of_bounds, vec![(1, 11)]);
+        assert!(analysis.loops.is_empty());
+
+        let analysis = analyze(&assemble("l: jnz a +100\njmp l")?);
+        assert_eq!(analysis.out_of_bounds, vec![(0, 100)]);
+        assert!(analysis.loops.is_empty());
+
+        let analysis = analyze(&assemble("l: jnz a +1\njmp l\njmp +100")?);
+        assert_eq!(analysis.out_of_bounds, vec![(2, 102)]);
+        assert_eq!(analysis.loops, vec![vec![0, 1]]);
+
+        Ok(())
+    }
 }
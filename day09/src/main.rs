@@ -1,72 +1,234 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::fs;
+use std::io::{self, BufRead};
+use std::ops::Range;
+
+fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().collect();
+    let preamble_len = match args.iter().position(|arg| arg == "--preamble") {
+        Some(i) => args
+            .get(i + 1)
+            .and_then(|n| n.parse().ok())
+            .ok_or("Expected a preamble length after \"--preamble\".")?,
+        None => 25,
+    };
+
+    if args.iter().any(|arg| arg == "--stream") {
+        let stdin = io::stdin();
+        for event in InvalidNumbers::new(stdin.lock(), preamble_len) {
+            let (line, n) = event?;
+            println!("Invalid number {} on line {}.", n, line);
+        }
+        return Ok(());
+    }
 
-fn main() -> Result<(), std::num::ParseIntError> {
     let contents = fs::read_to_string("input.txt").expect("Could not read file.");
     let numbers = contents
         .lines()
         .map(|l| l.parse::<u64>())
-        .collect::<Result<Vec<_>, _>>()?;
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
 
-    let invalid = scan(&numbers, 25).expect("No result found for part 1.");
+    let invalid = scan(&numbers, preamble_len).expect("No result found for part 1.");
     println!("Part 1: {}.", invalid);
     println!(
         "Part 2: {}.",
         scan2(&numbers, invalid).expect("No result found for part 2.")
     );
+
+    if args.iter().any(|arg| arg == "--ranges") {
+        for range in ranges_summing_to(&numbers, invalid, 2) {
+            println!("{:?}", range);
+        }
+        println!("Shortest: {:?}.", shortest_range(&numbers, invalid, 2));
+        println!("Longest: {:?}.", longest_range(&numbers, invalid, 2));
+    }
+
     Ok(())
 }
 
-fn scan(numbers: &Vec<u64>, preamble_len: usize) -> Option<u64> {
-    for window in numbers[..].windows(preamble_len + 1) {
-        let (last, rest) = window.split_last().unwrap();
+struct Window {
+    numbers: VecDeque<u64>,
+    counts: HashMap<u64, usize>,
+    size: usize,
+}
 
-        if !compare(&rest, *last) {
-            return Some(*last);
+impl Window {
+    fn new(size: usize) -> Self {
+        Self {
+            numbers: VecDeque::with_capacity(size + 1),
+            counts: HashMap::with_capacity(size),
+            size,
         }
     }
 
-    None
-}
+    fn is_full(&self) -> bool {
+        self.numbers.len() == self.size
+    }
+
+    fn is_valid(&self, n: u64) -> bool {
+        self.counts
+            .keys()
+            .any(|&x| x < n && n - x != x && self.counts.contains_key(&(n - x)))
+    }
 
-fn compare(xs: &[u64], n: u64) -> bool {
-    for x in xs {
-        for y in xs {
-            if x + y == n {
-                return true;
+    fn push(&mut self, n: u64) {
+        self.numbers.push_back(n);
+        *self.counts.entry(n).or_insert(0) += 1;
+
+        if self.numbers.len() > self.size {
+            if let Some(old) = self.numbers.pop_front() {
+                if let Some(count) = self.counts.get_mut(&old) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.counts.remove(&old);
+                    }
+                }
             }
         }
     }
+}
+
+fn invalid_numbers(numbers: &[u64], preamble_len: usize) -> Vec<u64> {
+    let mut window = Window::new(preamble_len);
+    let mut invalid = Vec::new();
+
+    for &n in numbers {
+        if window.is_full() && !window.is_valid(n) {
+            invalid.push(n);
+        }
+
+        window.push(n);
+    }
 
-    false
+    invalid
 }
 
-fn scan2(numbers: &Vec<u64>, invalid: u64) -> Option<u64> {
-    for i in 0..numbers.len() {
-        let mut sum = 0;
+struct InvalidNumbers<R> {
+    lines: io::Lines<R>,
+    window: Window,
+    line: usize,
+}
 
-        for (j, n) in numbers[i..].iter().enumerate() {
-            let j = i + j;
-            sum += n;
+impl<R: BufRead> InvalidNumbers<R> {
+    fn new(reader: R, preamble_len: usize) -> Self {
+        Self {
+            lines: reader.lines(),
+            window: Window::new(preamble_len),
+            line: 0,
+        }
+    }
+}
 
-            if sum > invalid {
-                break;
-            }
+impl<R: BufRead> Iterator for InvalidNumbers<R> {
+    type Item = Result<(usize, u64), String>;
 
-            if sum == invalid {
-                if let Some(max) = numbers[i..j + 1].iter().max() {
-                    if let Some(min) = numbers[i..j + 1].iter().min() {
-                        return Some(max + min);
-                    }
-                }
+    fn next(&mut self) -> Option<Self::Item> {
+        for line in &mut self.lines {
+            self.line += 1;
+
+            let n = match line {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => match line.trim().parse::<u64>() {
+                    Ok(n) => n,
+                    Err(e) => return Some(Err(format!("Line {}: {}.", self.line, e))),
+                },
+                Err(e) => return Some(Err(e.to_string())),
+            };
+
+            let invalid = self.window.is_full() && !self.window.is_valid(n);
+            self.window.push(n);
 
-                return None;
+            if invalid {
+                return Some(Ok((self.line, n)));
             }
         }
+
+        None
+    }
+}
+
+fn scan(numbers: &[u64], preamble_len: usize) -> Option<u64> {
+    invalid_numbers(numbers, preamble_len).first().cloned()
+}
+
+fn find_range(numbers: &[u64], target: u64) -> Option<Range<usize>> {
+    let mut start = 0;
+    let mut sum = 0;
+
+    for (end, n) in numbers.iter().enumerate() {
+        sum += n;
+
+        while sum > target {
+            sum -= numbers[start];
+            start += 1;
+        }
+
+        if sum == target && end > start {
+            return Some(start..end + 1);
+        }
     }
 
     None
 }
 
+fn scan2(numbers: &[u64], invalid: u64) -> Option<u64> {
+    let range = &numbers[find_range(numbers, invalid)?];
+    Some(range.iter().max()? + range.iter().min()?)
+}
+
+fn ranges_summing_to<T: Copy + Into<i128>>(
+    numbers: &[T],
+    target: T,
+    min_len: usize,
+) -> Vec<Range<usize>> {
+    let target = target.into();
+    let mut starts: HashMap<i128, Vec<usize>> = HashMap::new();
+    let mut ranges = Vec::new();
+    let mut sum = 0i128;
+
+    starts.insert(0, vec![0]);
+
+    for (i, &n) in numbers.iter().enumerate() {
+        sum += n.into();
+
+        if let Some(indices) = starts.get(&(sum - target)) {
+            ranges.extend(
+                indices
+                    .iter()
+                    .filter(|&&start| i + 1 - start >= min_len.max(1))
+                    .map(|&start| start..i + 1),
+            );
+        }
+
+        starts.entry(sum).or_default().push(i + 1);
+    }
+
+    ranges.sort_by_key(|r| (r.start, r.end));
+    ranges
+}
+
+fn shortest_range<T: Copy + Into<i128>>(
+    numbers: &[T],
+    target: T,
+    min_len: usize,
+) -> Option<Range<usize>> {
+    ranges_summing_to(numbers, target, min_len)
+        .into_iter()
+        .min_by_key(|r| r.len())
+}
+
+fn longest_range<T: Copy + Into<i128>>(
+    numbers: &[T],
+    target: T,
+    min_len: usize,
+) -> Option<Range<usize>> {
+    ranges_summing_to(numbers, target, min_len)
+        .into_iter()
+        .max_by_key(|r| r.len())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -81,6 +243,37 @@ mod tests {
         assert_eq!(scan(&input, 5), Some(127));
     }
 
+    #[test]
+    fn test_invalid_numbers() {
+        let input = vec![
+            35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277, 309,
+            576,
+        ];
+        assert_eq!(invalid_numbers(&input, 5), vec![127]);
+
+        // A number may not be paired with itself.
+        assert_eq!(invalid_numbers(&[1, 2, 3, 4], 2), vec![4]);
+        assert_eq!(invalid_numbers(&[2, 2, 4, 6], 2), vec![4]);
+    }
+
+    #[test]
+    fn test_invalid_numbers_stream() {
+        let input = "35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n\
+                     219\n299\n277\n309\n576\n";
+        let events: Vec<_> = InvalidNumbers::new(input.as_bytes(), 5).collect();
+        assert_eq!(events, vec![Ok((15, 127))]);
+
+        let events: Vec<_> = InvalidNumbers::new("1\n2\n\n3\n4\nfive\n9".as_bytes(), 2).collect();
+        assert_eq!(
+            events,
+            vec![
+                Ok((5, 4)),
+                Err("Line 6: invalid digit found in string.".to_string()),
+                Ok((7, 9)),
+            ]
+        );
+    }
+
     #[test]
     fn test_scan2() {
         let input = vec![
@@ -90,4 +283,35 @@ mod tests {
 
         assert_eq!(scan2(&input, 127), Some(62));
     }
+
+    #[test]
+    fn test_find_range() {
+        let input = vec![
+            35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277, 309,
+            576,
+        ];
+
+        assert_eq!(find_range(&input, 127), Some(2..6));
+        assert_eq!(find_range(&input, 35), Some(1..3));
+        assert_eq!(find_range(&input, 36), None);
+        assert_eq!(find_range(&input, 1), None);
+    }
+
+    #[test]
+    fn test_ranges_summing_to() {
+        let input: Vec<i64> = vec![1, -1, 2, 3, -2, 2];
+
+        assert_eq!(
+            ranges_summing_to(&input, 3, 1),
+            vec![0..5, 2..5, 3..4, 3..6]
+        );
+        assert_eq!(ranges_summing_to(&input, 3, 2), vec![0..5, 2..5, 3..6]);
+        assert_eq!(shortest_range(&input, 3, 1), Some(3..4));
+        assert_eq!(shortest_range(&input, 3, 2), Some(2..5));
+        assert_eq!(longest_range(&input, 3, 1), Some(0..5));
+        assert_eq!(longest_range(&input, 42, 1), None);
+
+        let input: Vec<u64> = vec![35, 20, 15, 25, 47, 40, 62, 55, 65];
+        assert_eq!(ranges_summing_to(&input, 127, 2), vec![2..6]);
+    }
 }
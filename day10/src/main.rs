@@ -1,62 +1,328 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
+use std::ops::AddAssign;
 
-fn main() -> Result<(), std::num::ParseIntError> {
+fn main() -> Result<(), String> {
     let contents = fs::read_to_string("input.txt").expect("Could not read file.");
     let numbers = contents
         .lines()
         .map(|l| l.parse::<u32>())
-        .collect::<Result<Vec<_>, _>>()?;
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let args: Vec<String> = std::env::args().collect();
+    let flag = |name: &str| -> Result<Option<u32>, String> {
+        match args.iter().position(|arg| arg == name) {
+            Some(i) => args
+                .get(i + 1)
+                .and_then(|n| n.parse().ok())
+                .map(Some)
+                .ok_or_else(|| format!("Expected a joltage after \"{}\".", name)),
+            None => Ok(None),
+        }
+    };
+
+    let tolerance = flag("--tolerance")?.unwrap_or(3);
+    let default = Setup::new(&numbers, tolerance);
+    let setup = Setup::with(
+        flag("--outlet")?.unwrap_or(default.outlet),
+        flag("--device")?.unwrap_or(default.device),
+        tolerance,
+    )?;
+
+    println!("Part 1: {}.", part1(&setup, &numbers));
+    println!("Part 2: {}.", part2(&setup, &numbers));
+
+    if let Some(i) = args.iter().position(|arg| arg == "--list") {
+        let n = args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(10);
+
+        for chain in setup.arrangements(&numbers).take(n) {
+            println!("{:?}", chain);
+        }
+    }
+
+    if let Some(i) = args.iter().position(|arg| arg == "--validate") {
+        let chain = args
+            .get(i + 1)
+            .ok_or("Expected a comma-separated chain after \"--validate\".")?
+            .split(',')
+            .map(|n| n.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        match setup.validate(&chain, &numbers) {
+            Ok(()) => println!("Chain is valid."),
+            Err(err) => println!("Chain is invalid: {}", err),
+        }
+    }
 
-    println!("Part 1: {}.", part1(&numbers));
-    println!("Part 2: {}.", part2(&numbers));
     Ok(())
 }
 
-fn part1(numbers: &Vec<u32>) -> u32 {
-    let numbers = &mut numbers.clone()[..];
-    numbers.sort_unstable();
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Setup {
+    outlet: u32,
+    device: u32,
+    tolerance: u32,
+}
 
-    let (one, three) =
-        numbers
-            .windows(2)
-            .fold((1, 1), |(one, three), window| match window[1] - window[0] {
-                1 => (one + 1, three),
-                3 => (one, three + 1),
-                _ => (one, three),
-            });
+#[derive(Debug, PartialEq)]
+enum ChainError {
+    MissingAdapter(u32),
+    OutOfRange(u32),
+    NotIncreasing(u32, u32),
+    GapTooLarge(u32, u32),
+}
 
-    one * three
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChainError::MissingAdapter(n) => write!(f, "there is no {} jolt adapter.", n),
+            ChainError::OutOfRange(n) => write!(
+                f,
+                "the {} jolt adapter is not between the outlet and the device.",
+                n
+            ),
+            ChainError::NotIncreasing(a, b) => {
+                write!(f, "{} jolts is not higher than {} jolts.", b, a)
+            }
+            ChainError::GapTooLarge(a, b) => {
+                write!(f, "the gap from {} to {} jolts is too large.", a, b)
+            }
+        }
+    }
 }
 
-fn part2(numbers: &Vec<u32>) -> u64 {
-    let numbers = &mut numbers.clone();
-    numbers.push(0);
-    numbers.push(*numbers.iter().max().unwrap() + 3);
-    numbers.sort_unstable();
-
-    let mut acc = 1;
-    let mut i = 0;
-    let mut j;
-
-    while i < numbers.len() {
-        j = i;
-        while j < numbers.len() {
-            let n = (j - i) as u32;
-            if numbers[j] - numbers[i] > n {
-                if n > 2 && n <= 4 {
-                    acc *= 2u64.pow(n - 2);
-                } else if n > 4 {
-                    acc *= 2u64.pow(n - 2) - ((n - 4) * (n - 4 + 1) / 2) as u64;
+/// The device is always rated this many jolts above the highest adapter.
+const DEVICE_OFFSET: u32 = 3;
+
+impl Setup {
+    fn new(numbers: &[u32], tolerance: u32) -> Self {
+        Self {
+            outlet: 0,
+            device: numbers.iter().max().unwrap_or(&0) + DEVICE_OFFSET,
+            tolerance,
+        }
+    }
+
+    fn with(outlet: u32, device: u32, tolerance: u32) -> Result<Self, String> {
+        if device <= outlet {
+            return Err(format!(
+                "Device joltage {} must be higher than outlet joltage {}.",
+                device, outlet
+            ));
+        }
+
+        Ok(Self {
+            outlet,
+            device,
+            tolerance,
+        })
+    }
+
+    fn joltages(&self, numbers: &[u32]) -> Vec<u32> {
+        let mut joltages: Vec<u32> = numbers
+            .iter()
+            .cloned()
+            .filter(|&n| n > self.outlet && n < self.device)
+            .collect();
+        joltages.push(self.outlet);
+        joltages.push(self.device);
+        joltages.sort_unstable();
+        joltages
+    }
+
+    fn differences(&self, numbers: &[u32]) -> HashMap<u32, u32> {
+        let mut differences = HashMap::new();
+
+        for window in self.joltages(numbers).windows(2) {
+            *differences.entry(window[1] - window[0]).or_insert(0) += 1;
+        }
+
+        differences
+    }
+
+    fn count_arrangements(&self, numbers: &[u32]) -> BigUint {
+        let joltages = self.joltages(numbers);
+        let mut ways = vec![BigUint::from(0); joltages.len()];
+        ways[0] = BigUint::from(1);
+
+        for i in 1..joltages.len() {
+            for j in (0..i).rev() {
+                let gap = joltages[i] - joltages[j];
+
+                if gap > self.tolerance {
+                    break;
+                } else if gap > 0 {
+                    let (done, rest) = ways.split_at_mut(i);
+                    rest[0] += &done[j];
                 }
-                break;
             }
-            j += 1;
         }
 
-        i = j
+        ways.pop().unwrap_or_else(|| BigUint::from(0))
     }
 
-    acc
+    fn arrangements(&self, numbers: &[u32]) -> Arrangements {
+        let joltages = self.joltages(numbers);
+        let last = joltages.len() - 1;
+
+        // Only joltages from which the device is still reachable are explored.
+        let mut reaches_device = vec![false; joltages.len()];
+        reaches_device[last] = true;
+
+        for i in (0..last).rev() {
+            reaches_device[i] = (i + 1..=last)
+                .take_while(|&j| joltages[j] - joltages[i] <= self.tolerance)
+                .any(|j| joltages[j] > joltages[i] && reaches_device[j]);
+        }
+
+        Arrangements {
+            stack: if reaches_device[0] {
+                vec![(0, 1)]
+            } else {
+                Vec::new()
+            },
+            joltages,
+            tolerance: self.tolerance,
+            reaches_device,
+        }
+    }
+
+    fn validate(&self, chain: &[u32], numbers: &[u32]) -> Result<(), ChainError> {
+        let mut available: HashMap<u32, usize> = HashMap::new();
+        for &n in numbers {
+            *available.entry(n).or_insert(0) += 1;
+        }
+
+        for &n in chain {
+            if n <= self.outlet || n >= self.device {
+                return Err(ChainError::OutOfRange(n));
+            }
+
+            match available.get_mut(&n) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => return Err(ChainError::MissingAdapter(n)),
+            }
+        }
+
+        let mut previous = self.outlet;
+
+        for &n in chain.iter().chain(&[self.device]) {
+            if n <= previous {
+                return Err(ChainError::NotIncreasing(previous, n));
+            } else if n - previous > self.tolerance {
+                return Err(ChainError::GapTooLarge(previous, n));
+            }
+            previous = n;
+        }
+
+        Ok(())
+    }
+}
+
+/// Unsigned integer of arbitrary size, stored as little-endian base 10^18
+/// limbs. Arrangement counts only ever need addition and printing.
+#[derive(Clone, Debug, PartialEq)]
+struct BigUint(Vec<u64>);
+
+const LIMB: u64 = 1_000_000_000_000_000_000;
+
+impl From<u128> for BigUint {
+    fn from(mut n: u128) -> Self {
+        let mut limbs = vec![(n % LIMB as u128) as u64];
+        n /= LIMB as u128;
+
+        while n > 0 {
+            limbs.push((n % LIMB as u128) as u64);
+            n /= LIMB as u128;
+        }
+
+        Self(limbs)
+    }
+}
+
+impl AddAssign<&BigUint> for BigUint {
+    fn add_assign(&mut self, other: &BigUint) {
+        let mut carry = 0;
+
+        for i in 0..self.0.len().max(other.0.len()) {
+            if i == self.0.len() {
+                self.0.push(0);
+            }
+
+            let sum = self.0[i] + other.0.get(i).unwrap_or(&0) + carry;
+            self.0[i] = sum % LIMB;
+            carry = sum / LIMB;
+        }
+
+        if carry > 0 {
+            self.0.push(carry);
+        }
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut limbs = self.0.iter().rev();
+        write!(f, "{}", limbs.next().unwrap_or(&0))?;
+
+        for limb in limbs {
+            write!(f, "{:018}", limb)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Arrangements {
+    joltages: Vec<u32>,
+    tolerance: u32,
+    reaches_device: Vec<bool>,
+    stack: Vec<(usize, usize)>,
+}
+
+impl Iterator for Arrangements {
+    type Item = Vec<u32>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let last = self.joltages.len() - 1;
+
+        while let Some(&(node, candidate)) = self.stack.last() {
+            if node == last {
+                let chain = self.stack[1..self.stack.len() - 1]
+                    .iter()
+                    .map(|&(i, _)| self.joltages[i])
+                    .collect();
+                self.stack.pop();
+                return Some(chain);
+            }
+
+            if candidate <= last && self.joltages[candidate] - self.joltages[node] <= self.tolerance
+            {
+                self.stack.last_mut()?.1 += 1;
+
+                if self.joltages[candidate] > self.joltages[node] && self.reaches_device[candidate]
+                {
+                    self.stack.push((candidate, candidate + 1));
+                }
+            } else {
+                self.stack.pop();
+            }
+        }
+
+        None
+    }
+}
+
+fn part1(setup: &Setup, numbers: &[u32]) -> u32 {
+    let differences = setup.differences(numbers);
+    differences.get(&1).unwrap_or(&0) * differences.get(&3).unwrap_or(&0)
+}
+
+fn part2(setup: &Setup, numbers: &[u32]) -> BigUint {
+    setup.count_arrangements(numbers)
 }
 
 #[cfg(test)]
@@ -66,24 +332,222 @@ mod tests {
     #[test]
     fn test_part1() {
         let input = vec![16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4];
-        assert_eq!(part1(&input), 35);
+        assert_eq!(part1(&Setup::new(&input, 3), &input), 35);
 
         let input = vec![
             28, 33, 18, 42, 31, 14, 46, 20, 48, 47, 24, 23, 49, 45, 19, 38, 39, 11, 1, 32, 25, 35,
             8, 17, 7, 9, 4, 2, 34, 10, 3,
         ];
-        assert_eq!(part1(&input), 220);
+        assert_eq!(part1(&Setup::new(&input, 3), &input), 220);
     }
 
     #[test]
     fn test_part2() {
         let input = vec![16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4];
-        assert_eq!(part2(&input), 8);
+        assert_eq!(part2(&Setup::new(&input, 3), &input), BigUint::from(8));
 
         let input = vec![
             28, 33, 18, 42, 31, 14, 46, 20, 48, 47, 24, 23, 49, 45, 19, 38, 39, 11, 1, 32, 25, 35,
             8, 17, 7, 9, 4, 2, 34, 10, 3,
         ];
-        assert_eq!(part2(&input), 19208);
+        assert_eq!(part2(&Setup::new(&input, 3), &input), BigUint::from(19208));
+    }
+
+    fn brute_force(numbers: &[u32], device: u32, tolerance: u32) -> u128 {
+        let mut count = 0;
+
+        for mask in 0..1u32 << numbers.len() {
+            let mut chain: Vec<u32> = (0..numbers.len())
+                .filter(|i| mask & (1 << i) != 0)
+                .map(|i| numbers[i])
+                .collect();
+            chain.push(0);
+            chain.push(device);
+            chain.sort_unstable();
+
+            if chain
+                .windows(2)
+                .all(|w| w[1] > w[0] && w[1] - w[0] <= tolerance)
+            {
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    #[test]
+    fn test_count_arrangements_brute_force() {
+        for set in 1..1u32 << 10 {
+            let numbers: Vec<u32> = (0..10)
+                .filter(|i| set & (1 << i) != 0)
+                .map(|i| i + 1)
+                .collect();
+
+            let max = numbers[numbers.len() - 1];
+            assert_eq!(Setup::new(&numbers, 1).device, max + 3);
+
+            for device in max + 1..=max + 4 {
+                for tolerance in 1..=4 {
+                    let setup = Setup::with(0, device, tolerance).unwrap();
+                    assert_eq!(
+                        setup.count_arrangements(&numbers),
+                        BigUint::from(brute_force(&numbers, device, tolerance)),
+                        "{:?} with device {} and tolerance {}",
+                        numbers,
+                        device,
+                        tolerance
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_arrangements() {
+        let input: Vec<u32> = (1..=20).collect();
+        assert_eq!(
+            Setup::new(&input, 3).count_arrangements(&input),
+            BigUint::from(121415)
+        );
+        assert_eq!(
+            Setup::with(0, 8, 2).unwrap().count_arrangements(&[2, 4, 6]),
+            BigUint::from(1)
+        );
+        assert_eq!(
+            Setup::new(&[2, 4, 6], 2).count_arrangements(&[2, 4, 6]),
+            BigUint::from(0)
+        );
+
+        let input: Vec<u32> = (1..=200).collect();
+        assert_eq!(
+            Setup::new(&input, 3).count_arrangements(&input).to_string(),
+            "52622583840983769603765180599790256716084480555530641"
+        );
+    }
+
+    #[test]
+    fn test_big_uint() {
+        let mut n = BigUint::from(u128::MAX);
+        assert_eq!(n.to_string(), u128::MAX.to_string());
+
+        n += &BigUint::from(1);
+        assert_eq!(n.to_string(), "340282366920938463463374607431768211456");
+
+        let mut n = BigUint::from(999_999_999_999_999_999);
+        n += &BigUint::from(1);
+        assert_eq!(n, BigUint(vec![0, 1]));
+        assert_eq!(n.to_string(), "1000000000000000000");
+        assert_eq!(BigUint::from(0).to_string(), "0");
+    }
+
+    #[test]
+    fn test_differences() {
+        let input = vec![16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4];
+        let setup = Setup::new(&input, 3);
+        assert_eq!(setup.differences(&input)[&1], 7);
+        assert_eq!(setup.differences(&input)[&3], 5);
+
+        let setup = Setup::with(2, 30, 3).unwrap();
+        assert_eq!(
+            setup.differences(&input),
+            [(2, 1), (1, 6), (3, 3), (11, 1)].iter().cloned().collect()
+        );
+        assert_eq!(part2(&setup, &input), BigUint::from(0));
+        assert_eq!(
+            part2(&Setup::with(2, 22, 3).unwrap(), &input),
+            BigUint::from(12)
+        );
+        assert_eq!(
+            Setup::with(5, 5, 3),
+            Err("Device joltage 5 must be higher than outlet joltage 5.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_arrangements() {
+        let input = vec![16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4];
+        let setup = Setup::new(&input, 3);
+        let chains: Vec<Vec<u32>> = setup.arrangements(&input).collect();
+
+        assert_eq!(chains.len(), 8);
+        assert_eq!(chains[0], vec![1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19]);
+        assert_eq!(chains[7], vec![1, 4, 7, 10, 12, 15, 16, 19]);
+        assert!(chains
+            .iter()
+            .all(|chain| setup.validate(chain, &input).is_ok()));
+
+        let input: Vec<u32> = (1..=200).collect();
+        let setup = Setup::new(&input, 3);
+        assert_eq!(
+            setup.arrangements(&input).nth(2),
+            Some((1..=197).chain(vec![199, 200]).collect())
+        );
+    }
+
+    #[test]
+    fn test_arrangements_unreachable_device() {
+        let input: Vec<u32> = (1..=40).collect();
+        let setup = Setup::with(0, 100, 3).unwrap();
+        assert_eq!(setup.count_arrangements(&input), BigUint::from(0));
+        assert_eq!(setup.arrangements(&input).next(), None);
+
+        let input = vec![1, 2, 3, 10];
+        let setup = Setup::new(&input, 3);
+        assert_eq!(setup.arrangements(&input).next(), None);
+
+        // Dead ends in the middle are skipped without losing live chains.
+        let input = vec![1, 2, 3, 4, 5, 6, 9, 30];
+        let setup = Setup::with(0, 12, 3).unwrap();
+        assert_eq!(
+            setup.arrangements(&input).count(),
+            setup
+                .count_arrangements(&input)
+                .to_string()
+                .parse()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let input = vec![16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4];
+        let setup = Setup::new(&input, 3);
+
+        assert_eq!(
+            setup.validate(&[1, 4, 7, 10, 12, 15, 16, 19], &input),
+            Ok(())
+        );
+        assert_eq!(
+            setup.validate(&[1, 4, 7, 10, 12, 15, 16], &input),
+            Err(ChainError::GapTooLarge(16, 22))
+        );
+        assert_eq!(
+            setup.validate(&[1, 4, 8, 10, 12, 15, 16, 19], &input),
+            Err(ChainError::MissingAdapter(8))
+        );
+        assert_eq!(
+            setup.validate(&[1, 4, 7, 5, 6, 10, 12, 15, 16, 19], &input),
+            Err(ChainError::NotIncreasing(7, 5))
+        );
+
+        let setup = Setup::with(4, 19, 3).unwrap();
+        assert_eq!(setup.validate(&[7, 10, 12, 15, 16], &input), Ok(()));
+        assert_eq!(
+            setup.validate(&[4, 7, 10, 12, 15, 16], &input),
+            Err(ChainError::OutOfRange(4))
+        );
+        assert_eq!(
+            setup.validate(&[7, 10, 12, 15, 16, 19], &input),
+            Err(ChainError::OutOfRange(19))
+        );
+        assert_eq!(setup.count_arrangements(&input), BigUint::from(8));
+        assert!(setup
+            .arrangements(&input)
+            .all(|chain| setup.validate(&chain, &input).is_ok()));
+        assert_eq!(
+            ChainError::GapTooLarge(16, 22).to_string(),
+            "the gap from 16 to 22 jolts is too large."
+        );
     }
 }
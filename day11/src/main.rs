@@ -1,4 +1,8 @@
+use std::collections::HashMap;
 use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum Position {
@@ -9,151 +13,388 @@ enum Position {
 
 type Grid = Vec<Vec<Position>>;
 
-fn main() -> Result<(), std::num::ParseIntError> {
-    let contents = fs::read_to_string("input.txt").expect("Could not read file.");
-    let mut grid = parse_grid(&contents);
+fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().collect();
+    let option = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .map(|i| args.get(i + 1).map(String::as_str))
+    };
+    let path = match option("--input") {
+        Some(path) => path.ok_or("Expected a path after \"--input\".")?,
+        None => "input.txt",
+    };
+    let frames = match option("--frames") {
+        Some(dir) => Some(Path::new(
+            dir.ok_or("Expected a directory after \"--frames\".")?,
+        )),
+        None => None,
+    };
+    let print = args.iter().any(|arg| arg == "--print");
+
+    let contents = fs::read_to_string(path).expect("Could not read file.");
+    let mut grid = parse_grid(&contents)?;
 
     println!("Part 1: {}.", run(&mut grid));
-    let mut grid = parse_grid(&contents);
+    if print {
+        print!("{}", format_grid(&grid));
+    }
+    let mut grid = parse_grid(&contents)?;
     println!("Part 2: {}.", run2(&mut grid));
+    if print {
+        print!("{}", format_grid(&grid));
+    }
+
+    if let Some(dir) = frames {
+        let grid = parse_grid(&contents)?;
+        let automata = [
+            Automaton::seating(Neighborhood::Adjacent, 4),
+            Automaton::seating(Neighborhood::LineOfSight, 5),
+        ];
+
+        for (part, automaton) in (1..).zip(&automata) {
+            let history = automaton.record(&grid);
+            history
+                .write_frames(dir, &format!("part{}", part))
+                .map_err(|e| e.to_string())?;
+
+            match history.outcome {
+                Outcome::Stable(generation) => {
+                    println!("Part {} stabilizes after {} generations.", part, generation)
+                }
+                Outcome::Cycle { start, period } => println!(
+                    "Part {} oscillates with period {} from generation {}.",
+                    part, period, start
+                ),
+            }
+        }
+    }
+
     Ok(())
 }
 
-fn parse_grid(input: &str) -> Grid {
-    let mut iter = input.lines();
-    let width = iter.next().unwrap_or("").len();
-    let height = iter.count() + 1;
-    let mut grid = Vec::with_capacity(height);
+impl Position {
+    fn symbol(self) -> char {
+        match self {
+            Position::Empty => 'L',
+            Position::Occupied => '#',
+            Position::Floor => '.',
+        }
+    }
+}
+
+fn parse_grid(input: &str) -> Result<Grid, String> {
+    let mut grid: Grid = Vec::new();
 
     for (y, line) in input.lines().enumerate() {
-        grid.push(Vec::with_capacity(width));
-
-        for (x, chr) in line.bytes().enumerate() {
-            grid[y].push(match chr {
-                b'L' => Position::Empty,
-                b'.' => Position::Floor,
-                _ => panic!("Invalid input '{}' on line {}.", chr, x),
+        let row = line
+            .chars()
+            .enumerate()
+            .map(|(x, chr)| match chr {
+                'L' => Ok(Position::Empty),
+                '#' => Ok(Position::Occupied),
+                '.' => Ok(Position::Floor),
+                _ => Err(format!(
+                    "Invalid input '{}' on row {}, column {}.",
+                    chr,
+                    y + 1,
+                    x + 1
+                )),
             })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(first) = grid.first() {
+            if row.len() != first.len() {
+                return Err(format!(
+                    "Row {} has {} columns, expected {}.",
+                    y + 1,
+                    row.len(),
+                    first.len()
+                ));
+            }
         }
+
+        grid.push(row);
     }
 
-    grid
+    Ok(grid)
 }
 
-fn count_occupied(grid: &Grid) -> usize {
+fn format_grid(grid: &Grid) -> String {
     grid.iter()
-        .flat_map(|r| r.iter())
-        .filter(|val| **val == Position::Occupied)
-        .count()
+        .map(|row| {
+            row.iter()
+                .map(|p| p.symbol())
+                .chain(Some('\n'))
+                .collect::<String>()
+        })
+        .collect()
+}
+
+const DIRECTIONS: [(i32, i32); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Neighborhood {
+    Adjacent,
+    LineOfSight,
 }
 
-fn get_neighbors(x: usize, y: usize, width: usize, height: usize) -> Vec<(usize, usize)> {
-    let mut neighbors = Vec::new();
+impl Neighborhood {
+    fn neighbors(self, grid: &Grid, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let width = grid[0].len() as i32;
+        let height = grid.len() as i32;
+        let mut neighbors = Vec::new();
+
+        for (x_off, y_off) in &DIRECTIONS {
+            let mut x_neig = x as i32 + x_off;
+            let mut y_neig = y as i32 + y_off;
 
-    for i in x.saturating_sub(1)..(x + 2).min(width) {
-        for j in y.saturating_sub(1)..(y + 2).min(height) {
-            if (i, j) != (x, y) {
-                neighbors.push((i, j));
+            while x_neig >= 0 && x_neig < width && y_neig >= 0 && y_neig < height {
+                if self == Neighborhood::Adjacent
+                    || grid[y_neig as usize][x_neig as usize] != Position::Floor
+                {
+                    neighbors.push((x_neig as usize, y_neig as usize));
+                    break;
+                }
+
+                x_neig += x_off;
+                y_neig += y_off;
             }
         }
+
+        neighbors
     }
+}
 
-    neighbors
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    birth: Range<usize>,
+    survival: Range<usize>,
 }
 
-fn update_grid(grid: &mut Grid) -> bool {
-    let width = grid[0].len();
-    let height = grid.len();
-    let mut changed = false;
-    let mut new_grid = grid.clone();
+#[derive(Clone, Debug, PartialEq)]
+struct Automaton {
+    neighborhood: Neighborhood,
+    rule: Rule,
+}
 
-    for y in 0..height {
-        for x in 0..width {
-            let val = grid[y][x];
-            let n = get_neighbors(x, y, width, height)
-                .iter()
-                .filter(|(x2, y2)| grid[*y2][*x2] == Position::Occupied)
-                .count();
-
-            if val == Position::Empty && n == 0 {
-                new_grid[y][x] = Position::Occupied;
-                changed = true;
-            } else if val == Position::Occupied && n >= 4 {
-                new_grid[y][x] = Position::Empty;
-                changed = true;
+impl Automaton {
+    fn seating(neighborhood: Neighborhood, tolerance: usize) -> Self {
+        Self {
+            neighborhood,
+            rule: Rule {
+                birth: 0..1,
+                survival: 0..tolerance,
+            },
+        }
+    }
+
+    fn run(&self, grid: &mut Grid) -> usize {
+        let history = self.record(grid);
+        let last = history.states.len() - 1;
+        *grid = history.frame(last);
+        history.count_occupied(last)
+    }
+
+    fn record(&self, grid: &Grid) -> History {
+        let mut simulation = Simulation::new(self, grid);
+        let mut seen = HashMap::new();
+        let mut states = Vec::new();
+
+        loop {
+            if let Some(&start) = seen.get(&simulation.occupied) {
+                let period = states.len() - start;
+                let outcome = if period == 1 {
+                    Outcome::Stable(start)
+                } else {
+                    Outcome::Cycle { start, period }
+                };
+
+                return History {
+                    simulation,
+                    states,
+                    outcome,
+                };
             }
+
+            seen.insert(simulation.occupied.clone(), states.len());
+            states.push(simulation.occupied.clone());
+            simulation.step();
         }
     }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Outcome {
+    Stable(usize),
+    Cycle { start: usize, period: usize },
+}
 
-    std::mem::swap(grid, &mut new_grid);
-    changed
+struct History {
+    simulation: Simulation,
+    states: Vec<Vec<u8>>,
+    outcome: Outcome,
 }
 
-fn run(grid: &mut Grid) -> usize {
-    while update_grid(grid) {}
-    count_occupied(grid)
-}
-
-fn update_grid2(grid: &mut Grid) -> bool {
-    let width = grid[0].len() as i32;
-    let height = grid.len() as i32;
-    let directions = [
-        (0, 1),
-        (1, 1),
-        (1, 0),
-        (1, -1),
-        (0, -1),
-        (-1, -1),
-        (-1, 0),
-        (-1, 1),
-    ];
-    let mut changed = false;
-    let mut new_grid = grid.clone();
-
-    for y in 0..height {
-        for x in 0..width {
-            let mut occupied = 0;
-
-            for (x_off, y_off) in &directions {
-                let mut x_neig = x + x_off;
-                let mut y_neig = y + y_off;
-                let mut radius = 1;
-
-                while x_neig >= 0 && x_neig < width && y_neig >= 0 && y_neig < height {
-                    match grid[y_neig as usize][x_neig as usize] {
-                        Position::Empty => {
-                            break;
-                        }
-                        Position::Occupied => {
-                            occupied += 1;
-                            break;
-                        }
-                        _ => {
-                            radius += 1;
-                            x_neig = x + radius * x_off;
-                            y_neig = y + radius * y_off;
-                        }
-                    }
+impl History {
+    fn frame(&self, generation: usize) -> Grid {
+        self.simulation.grid_from(&self.states[generation])
+    }
+
+    fn count_occupied(&self, generation: usize) -> usize {
+        self.states[generation]
+            .iter()
+            .filter(|&&val| val == 1)
+            .count()
+    }
+
+    fn write_frames(&self, dir: &Path, name: &str) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+
+        for generation in 0..self.states.len() {
+            let path = dir.join(format!("{}-{:04}.txt", name, generation));
+            fs::write(path, format_grid(&self.frame(generation)))?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Simulation {
+    width: usize,
+    cells: Vec<Position>,
+    seats: Vec<usize>,
+    adjacency: Vec<[u32; 8]>,
+    birth: [bool; 9],
+    survival: [bool; 9],
+    counts: Vec<u8>,
+    occupied: Vec<u8>,
+    next: Vec<u8>,
+}
+
+impl Simulation {
+    fn new(automaton: &Automaton, grid: &Grid) -> Self {
+        let width = grid.first().map_or(0, |row| row.len());
+        let cells: Vec<Position> = grid.iter().flatten().cloned().collect();
+        let seats: Vec<usize> = (0..cells.len())
+            .filter(|&i| cells[i] != Position::Floor)
+            .collect();
+        let mut seat_index = vec![usize::MAX; cells.len()];
+        for (k, &i) in seats.iter().enumerate() {
+            seat_index[i] = k;
+        }
+
+        // Missing neighbors point at a sentinel seat past the end that is never occupied.
+        let sentinel = seats.len() as u32;
+        let adjacency: Vec<[u32; 8]> = seats
+            .iter()
+            .map(|&i| {
+                let mut neighbors = [sentinel; 8];
+                let seats = automaton
+                    .neighborhood
+                    .neighbors(grid, i % width, i / width)
+                    .into_iter()
+                    .map(|(x, y)| seat_index[y * width + x])
+                    .filter(|&k| k != usize::MAX);
+
+                for (slot, k) in neighbors.iter_mut().zip(seats) {
+                    *slot = k as u32;
                 }
+                neighbors
+            })
+            .collect();
+
+        let mut birth = [false; 9];
+        let mut survival = [false; 9];
+        for n in 0..9 {
+            birth[n] = automaton.rule.birth.contains(&n);
+            survival[n] = automaton.rule.survival.contains(&n);
+        }
+
+        let occupied: Vec<u8> = seats
+            .iter()
+            .map(|&i| (cells[i] == Position::Occupied) as u8)
+            .chain(Some(0))
+            .collect();
+        let mut counts = vec![0u8; occupied.len()];
+        for (k, neighbors) in adjacency.iter().enumerate() {
+            counts[k] = neighbors.iter().map(|&j| occupied[j as usize]).sum();
+        }
+
+        Self {
+            width,
+            cells,
+            seats,
+            adjacency,
+            birth,
+            survival,
+            counts,
+            next: occupied.clone(),
+            occupied,
+        }
+    }
+
+    fn step(&mut self) -> bool {
+        let mut changed = false;
+
+        for k in 0..self.adjacency.len() {
+            let n = self.counts[k] as usize;
+            self.next[k] = if self.occupied[k] == 0 {
+                self.birth[n] as u8
+            } else {
+                self.survival[n] as u8
+            };
+        }
+
+        for (k, neighbors) in self.adjacency.iter().enumerate() {
+            if self.next[k] == self.occupied[k] {
+                continue;
             }
 
-            if grid[y as usize][x as usize] == Position::Empty && occupied == 0 {
-                new_grid[y as usize][x as usize] = Position::Occupied;
-                changed = true;
-            } else if grid[y as usize][x as usize] == Position::Occupied && occupied >= 5 {
-                new_grid[y as usize][x as usize] = Position::Empty;
-                changed = true;
+            let delta = if self.next[k] == 1 { 1 } else { u8::MAX };
+            for &j in neighbors {
+                self.counts[j as usize] = self.counts[j as usize].wrapping_add(delta);
             }
+            changed = true;
+        }
+
+        std::mem::swap(&mut self.occupied, &mut self.next);
+        changed
+    }
+
+    fn grid_from(&self, occupied: &[u8]) -> Grid {
+        let mut cells = self.cells.clone();
+
+        for (&i, &occupied) in self.seats.iter().zip(occupied) {
+            cells[i] = if occupied == 1 {
+                Position::Occupied
+            } else {
+                Position::Empty
+            };
         }
+
+        cells
+            .chunks(self.width.max(1))
+            .map(|row| row.to_vec())
+            .collect()
     }
+}
 
-    std::mem::swap(grid, &mut new_grid);
-    changed
+fn run(grid: &mut Grid) -> usize {
+    Automaton::seating(Neighborhood::Adjacent, 4).run(grid)
 }
 
 fn run2(grid: &mut Grid) -> usize {
-    while update_grid2(grid) {}
-    count_occupied(grid)
+    Automaton::seating(Neighborhood::LineOfSight, 5).run(grid)
 }
 
 #[cfg(test)]
@@ -175,8 +416,8 @@ L.LLLLL.LL"
     }
 
     #[test]
-    fn test_parse_grid() {
-        let parsed = parse_grid(get_input());
+    fn test_parse_grid() -> Result<(), String> {
+        let parsed = parse_grid(get_input())?;
         assert_eq!(parsed.len(), 10);
         assert_eq!(parsed[0].len(), 10);
         assert_eq!(
@@ -187,17 +428,153 @@ L.LLLLL.LL"
                 .count(),
             71
         );
+
+        Ok(())
     }
 
     #[test]
-    fn test_run() {
-        let mut grid = parse_grid(get_input());
+    fn test_run() -> Result<(), String> {
+        let mut grid = parse_grid(get_input())?;
         assert_eq!(run(&mut grid), 37);
+
+        Ok(())
     }
 
     #[test]
-    fn test_run2() {
-        let mut grid = parse_grid(get_input());
+    fn test_run2() -> Result<(), String> {
+        let mut grid = parse_grid(get_input())?;
         assert_eq!(run2(&mut grid), 26);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_neighbors() -> Result<(), String> {
+        let grid = parse_grid(get_input())?;
+
+        assert_eq!(
+            Neighborhood::Adjacent.neighbors(&grid, 0, 0),
+            vec![(0, 1), (1, 1), (1, 0)]
+        );
+        assert_eq!(
+            Neighborhood::LineOfSight.neighbors(&grid, 0, 0),
+            vec![(0, 1), (1, 1), (2, 0)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_automaton() -> Result<(), String> {
+        // A rule that never lets anyone leave fills every seat.
+        let automaton = Automaton {
+            neighborhood: Neighborhood::Adjacent,
+            rule: Rule {
+                birth: 0..9,
+                survival: 0..9,
+            },
+        };
+        let mut grid = parse_grid(get_input())?;
+        assert_eq!(automaton.run(&mut grid), 71);
+
+        let grid = parse_grid(get_input())?;
+        let history = Automaton::seating(Neighborhood::Adjacent, 4).record(&grid);
+        assert_eq!(history.outcome, Outcome::Stable(5));
+        assert_eq!(history.count_occupied(1), 71);
+
+        let zero = Automaton::seating(Neighborhood::Adjacent, 0).record(&grid);
+        assert_eq!(
+            zero.outcome,
+            Outcome::Cycle {
+                start: 0,
+                period: 2
+            }
+        );
+        assert_eq!(zero.count_occupied(0), 0);
+        assert_eq!(zero.count_occupied(1), 71);
+        assert_eq!(history.count_occupied(2), 20);
+        assert_eq!(
+            history.frame(2)[0][..4],
+            [
+                Position::Occupied,
+                Position::Floor,
+                Position::Empty,
+                Position::Empty
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_grid_errors() {
+        assert_eq!(
+            parse_grid("L.L\nL#x\n").unwrap_err(),
+            "Invalid input 'x' on row 2, column 3."
+        );
+        assert_eq!(
+            parse_grid("L.L\nL#\n").unwrap_err(),
+            "Row 2 has 2 columns, expected 3."
+        );
+    }
+
+    #[test]
+    fn test_format_grid() -> Result<(), String> {
+        let input = "#.##.L#.##\n#L###LL.L#\nL.#.#..#..\n";
+        assert_eq!(format_grid(&parse_grid(input)?), input);
+
+        let mut grid = parse_grid(get_input())?;
+        run(&mut grid);
+        let mut resumed = parse_grid(&format_grid(&grid))?;
+        assert_eq!(resumed, grid);
+        assert_eq!(run(&mut resumed), 37);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_cycle() -> Result<(), String> {
+        let life = Automaton {
+            neighborhood: Neighborhood::Adjacent,
+            rule: Rule {
+                birth: 3..4,
+                survival: 2..4,
+            },
+        };
+        let mut grid = parse_grid("LLLLL\nLLLLL\nL###L\nLLLLL\nLLLLL")?;
+        let history = life.record(&grid);
+
+        assert_eq!(
+            history.outcome,
+            Outcome::Cycle {
+                start: 0,
+                period: 2
+            }
+        );
+        assert_eq!(
+            format_grid(&history.frame(1)),
+            "LLLLL\nLL#LL\nLL#LL\nLL#LL\nLLLLL\n"
+        );
+        assert_eq!(life.run(&mut grid), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_frames() -> Result<(), String> {
+        let dir = std::env::temp_dir().join(format!("day11-frames-{}", std::process::id()));
+        let history =
+            Automaton::seating(Neighborhood::Adjacent, 4).record(&parse_grid(get_input())?);
+
+        history
+            .write_frames(&dir, "test")
+            .map_err(|e| e.to_string())?;
+        let frame = fs::read_to_string(dir.join("test-0002.txt")).map_err(|e| e.to_string())?;
+        assert_eq!(frame.lines().next(), Some("#.LL.L#.##"));
+        assert!(dir.join("test-0005.txt").exists());
+        assert!(!dir.join("test-0006.txt").exists());
+
+        fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+        Ok(())
     }
 }
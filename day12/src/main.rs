@@ -1,181 +1,402 @@
+use std::fmt;
 use std::fs;
+use std::str::FromStr;
 
-mod part1 {
-    #[derive(Clone, Copy, Debug, PartialEq)]
-    pub enum Direction {
-        North = 0,
-        East,
-        South,
-        West,
-    }
-    use Direction::*;
-    impl Direction {
-        fn from_i32(value: i32) -> Direction {
-            match value {
-                0 => North,
-                1 => East,
-                2 => South,
-                3 => West,
-                _ => panic!("Unknown value: {}", value),
-            }
-        }
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
     }
 
-    #[derive(Debug, PartialEq)]
-    pub struct Ship {
-        pub x: i32,
-        pub y: i32,
-        pub d: Direction,
-    }
-    impl Ship {
-        fn sail(&mut self, direction: Direction, value: i32) {
-            match direction {
-                North => self.y += value,
-                East => self.x += value,
-                South => self.y -= value,
-                West => self.x -= value,
-            };
+    fn rotate(self, quarter_turns: i32) -> Self {
+        match quarter_turns.rem_euclid(4) {
+            0 => self,
+            1 => Self::new(-self.y, self.x),
+            2 => Self::new(-self.x, -self.y),
+            _ => Self::new(self.y, -self.x),
         }
     }
-    fn step(ship: &mut Ship, line: &str) {
-        let (action, value) = line.split_at(1);
-        let value = value
+
+    fn scale(self, n: i32) -> Self {
+        Self::new(self.x * n, self.y * n)
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y)
+    }
+
+    fn manhattan(self) -> i32 {
+        self.x.abs() + self.y.abs()
+    }
+
+    fn distance(self, other: Self) -> f64 {
+        f64::from(self.x - other.x).hypot(f64::from(self.y - other.y))
+    }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+const NORTH: Point = Point { x: 0, y: 1 };
+const EAST: Point = Point { x: 1, y: 0 };
+const SOUTH: Point = Point { x: 0, y: -1 };
+const WEST: Point = Point { x: -1, y: 0 };
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Instruction {
+    Shift(Point, i32),
+    Turn(i32),
+    SetHeading(i32),
+    Forward(i32),
+}
+
+fn quarter_turns(degrees: i32) -> Result<i32, String> {
+    if degrees % 90 == 0 {
+        Ok(degrees / 90)
+    } else {
+        Err(format!(
+            "Angle {} is not a multiple of 90 degrees.",
+            degrees
+        ))
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.trim().chars();
+        let action = chars.next().ok_or("Empty instruction.")?;
+        let value = chars
+            .as_str()
             .parse::<i32>()
-            .expect(&format!("Could not parse line \"{}\"", line));
-
-        match action.as_bytes()[0] {
-            b'N' => ship.sail(North, value),
-            b'E' => ship.sail(East, value),
-            b'S' => ship.sail(South, value),
-            b'W' => ship.sail(West, value),
-            b'L' => ship.d = Direction::from_i32((ship.d as i32 + 4 - value / 90) % 4) as Direction,
-            b'R' => ship.d = Direction::from_i32((ship.d as i32 + value / 90) % 4),
-            b'F' => ship.sail(ship.d, value),
-            _ => panic!("Invalid input on line \"{}\".", line),
+            .map_err(|_| format!("Invalid value \"{}\".", chars.as_str()))?;
+
+        match action {
+            'N' => Ok(Self::Shift(NORTH, value)),
+            'E' => Ok(Self::Shift(EAST, value)),
+            'S' => Ok(Self::Shift(SOUTH, value)),
+            'W' => Ok(Self::Shift(WEST, value)),
+            'L' => Ok(Self::Turn(quarter_turns(value)?)),
+            'R' => Ok(Self::Turn(-quarter_turns(value)?)),
+            // Compass bearing, clockwise from north.
+            'H' => Ok(Self::SetHeading((1 - quarter_turns(value)?).rem_euclid(4))),
+            'F' => Ok(Self::Forward(value)),
+            'B' => Ok(Self::Forward(-value)),
+            _ => Err(format!("Unknown action '{}'.", action)),
         }
     }
+}
 
-    pub fn run(input: &str) -> Ship {
-        let mut ship = Ship {
-            x: 0,
-            y: 0,
-            d: East,
-        };
+fn parse(input: &str) -> Result<Vec<Instruction>, String> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(n, line)| line.parse().map_err(|e| format!("Line {}: {}", n + 1, e)))
+        .collect()
+}
 
-        for line in input.lines() {
-            step(&mut ship, line);
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Model {
+    Heading,
+    Waypoint,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ship {
+    pub pos: Point,
+    pub vector: Point,
+    /// Quarter turns counter-clockwise from east.
+    pub heading: i32,
+}
+
+impl Ship {
+    fn new(model: Model) -> Self {
+        Self {
+            pos: Point::new(0, 0),
+            vector: match model {
+                Model::Heading => EAST,
+                Model::Waypoint => Point::new(10, 1),
+            },
+            heading: 0,
         }
+    }
 
-        ship
+    fn step(&mut self, model: Model, instruction: Instruction) {
+        match (model, instruction) {
+            (Model::Heading, Instruction::Shift(direction, n)) => {
+                self.pos = self.pos.add(direction.scale(n))
+            }
+            (Model::Waypoint, Instruction::Shift(direction, n)) => {
+                self.vector = self.vector.add(direction.scale(n))
+            }
+            (_, Instruction::Turn(quarter_turns)) => self.turn(quarter_turns),
+            (_, Instruction::SetHeading(heading)) => self.turn(heading - self.heading),
+            (_, Instruction::Forward(n)) => self.pos = self.pos.add(self.vector.scale(n)),
+        }
+    }
+
+    fn turn(&mut self, quarter_turns: i32) {
+        self.vector = self.vector.rotate(quarter_turns);
+        self.heading = (self.heading + quarter_turns).rem_euclid(4);
     }
+}
 
-    #[cfg(test)]
-    mod tests {
-        use super::*;
+fn navigate(instructions: &[Instruction], model: Model) -> Ship {
+    let mut ship = Ship::new(model);
 
-        #[test]
-        fn test_part1() {
-            let input = "F10\nN3\nF7\nR90\nF11";
-            assert_eq!(
-                run(input),
-                Ship {
-                    x: 17,
-                    y: -8,
-                    d: South
-                }
-            )
-        }
+    for &instruction in instructions {
+        ship.step(model, instruction);
     }
+
+    ship
 }
 
-mod part2 {
-    #[derive(Debug, PartialEq)]
-    pub struct Point {
-        pub x: i32,
-        pub y: i32,
+fn trace(instructions: &[Instruction], model: Model) -> Route {
+    let mut ship = Ship::new(model);
+    let mut ships = vec![ship];
+
+    for &instruction in instructions {
+        ship.step(model, instruction);
+        ships.push(ship);
     }
-    impl Point {
-        fn rotate(&mut self, deg: i32) {
-            let (sin, cos) = (deg as f32 / 180.0 * core::f32::consts::PI).sin_cos();
-            let (sin, cos) = (sin as i32, cos as i32);
 
-            let x = self.x * cos - self.y * sin;
-            let y = self.x * sin + self.y * cos;
-            self.x = x;
-            self.y = y;
-        }
+    Route { ships }
+}
+
+impl fmt::Display for Ship {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "position {}, waypoint {}", self.pos, self.vector)
     }
+}
+
+struct Route {
+    ships: Vec<Ship>,
+}
 
-    #[derive(Debug, PartialEq)]
-    pub struct Ship {
-        pub pos: Point,
-        pub waypoint: Point,
+impl Route {
+    fn positions(&self) -> impl Iterator<Item = Point> + '_ {
+        self.ships.iter().map(|ship| ship.pos)
     }
-    impl Ship {
-        fn sail(&mut self, n: i32) {
-            self.pos.x += self.waypoint.x * n;
-            self.pos.y += self.waypoint.y * n;
-        }
+
+    fn total_distance(&self) -> f64 {
+        self.ships
+            .windows(2)
+            .map(|pair| pair[0].pos.distance(pair[1].pos))
+            .sum()
     }
 
-    fn step(ship: &mut Ship, line: &str) {
-        let (action, value) = line.split_at(1);
-        let value = value
-            .parse::<i32>()
-            .expect(&format!("Could not parse line \"{}\"", line));
-
-        match action.as_bytes()[0] {
-            b'N' => ship.waypoint.y += value,
-            b'E' => ship.waypoint.x += value,
-            b'S' => ship.waypoint.y -= value,
-            b'W' => ship.waypoint.x -= value,
-            b'L' => ship.waypoint.rotate(value),
-            b'R' => ship.waypoint.rotate(-value),
-            b'F' => ship.sail(value),
-            _ => panic!("Invalid input on line \"{}\".", line),
-        }
+    fn bounding_box(&self) -> (Point, Point) {
+        self.positions()
+            .fold((self.ships[0].pos, self.ships[0].pos), |(min, max), p| {
+                (
+                    Point::new(min.x.min(p.x), min.y.min(p.y)),
+                    Point::new(max.x.max(p.x), max.y.max(p.y)),
+                )
+            })
+    }
 
-        println!("{:?}", ship);
+    fn furthest_point(&self) -> Point {
+        self.positions()
+            .max_by_key(|p| p.manhattan())
+            .unwrap_or_else(|| Point::new(0, 0))
     }
 
-    pub fn run(input: &str) -> Ship {
-        let mut ship = Ship {
-            pos: Point { x: 0, y: 0 },
-            waypoint: Point { x: 10, y: 1 },
-        };
+    fn to_svg(&self) -> String {
+        let ship = self.ships[self.ships.len() - 1];
+        let waypoint = ship.pos.add(ship.vector);
+        let (min, max) = self.bounding_box();
+        let min = Point::new(min.x.min(waypoint.x), min.y.min(waypoint.y));
+        let max = Point::new(max.x.max(waypoint.x), max.y.max(waypoint.y));
+        let margin = (max.x - min.x).max(max.y - min.y) / 20 + 1;
 
-        for line in input.lines() {
-            step(&mut ship, line);
-        }
+        // SVG's y axis points down, so north is flipped to negative y.
+        let mut points: Vec<Point> = self.positions().collect();
+        points.dedup();
+        let points: Vec<String> = points.iter().map(|p| format!("{},{}", p.x, -p.y)).collect();
 
-        ship
+        format!(
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+                "  <polyline points=\"{}\" fill=\"none\" stroke=\"black\" ",
+                "vector-effect=\"non-scaling-stroke\"/>\n",
+                "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"red\" ",
+                "vector-effect=\"non-scaling-stroke\"/>\n",
+                "</svg>\n"
+            ),
+            min.x - margin,
+            -max.y - margin,
+            max.x - min.x + 2 * margin,
+            max.y - min.y + 2 * margin,
+            points.join(" "),
+            ship.pos.x,
+            -ship.pos.y,
+            waypoint.x,
+            -waypoint.y,
+        )
     }
+}
 
-    #[cfg(test)]
-    mod tests {
-        use super::*;
+fn main() -> Result<(), String> {
+    let input = fs::read_to_string("input.txt").expect("Could not read file.");
+    let instructions = parse(&input)?;
 
-        #[test]
-        fn test_part2() {
-            let input = "F10\nN3\nF7\nR90\nF11";
-            assert_eq!(
-                run(input),
-                Ship {
-                    pos: Point { x: 214, y: -72 },
-                    waypoint: Point { x: 4, y: -10 },
-                }
-            )
+    let ship = navigate(&instructions, Model::Heading);
+    println!("Part 1: {}.", ship.pos.manhattan());
+    let ship = navigate(&instructions, Model::Waypoint);
+    println!("Part 2: {}.", ship.pos.manhattan());
+
+    let args: Vec<String> = std::env::args().collect();
+    let model = if args.iter().any(|arg| arg == "--heading") {
+        Model::Heading
+    } else {
+        Model::Waypoint
+    };
+    let route = trace(&instructions, model);
+
+    if args.iter().any(|arg| arg == "--trace") {
+        for ship in &route.ships {
+            println!("{}", ship);
         }
+
+        let (min, max) = route.bounding_box();
+        println!("Total distance: {:.2}.", route.total_distance());
+        println!("Bounding box: {} to {}.", min, max);
+        println!("Furthest point: {}.", route.furthest_point());
     }
+
+    if let Some(i) = args.iter().position(|arg| arg == "--svg") {
+        let path = args.get(i + 1).ok_or("Expected a path after \"--svg\".")?;
+        fs::write(path, route.to_svg()).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
 }
 
-use part1::run as part1;
-use part2::run as part2;
-fn main() {
-    let input = fs::read_to_string("input.txt").expect("Could not read file.");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate() {
+        let point = Point::new(10, 4);
+        assert_eq!(point.rotate(1), Point::new(-4, 10));
+        assert_eq!(point.rotate(2), Point::new(-10, -4));
+        assert_eq!(point.rotate(-1), Point::new(4, -10));
+        assert_eq!(point.rotate(3), point.rotate(-1));
+        assert_eq!(point.rotate(4), point);
+    }
+
+    #[test]
+    fn test_part1() -> Result<(), String> {
+        let input = "F10\nN3\nF7\nR90\nF11";
+        assert_eq!(
+            navigate(&parse(input)?, Model::Heading),
+            Ship {
+                pos: Point::new(17, -8),
+                vector: SOUTH,
+                heading: 3,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_part2() -> Result<(), String> {
+        let input = "F10\nN3\nF7\nR90\nF11";
+        assert_eq!(
+            navigate(&parse(input)?, Model::Waypoint),
+            Ship {
+                pos: Point::new(214, -72),
+                vector: Point::new(4, -10),
+                heading: 3,
+            }
+        );
+
+        Ok(())
+    }
 
-    let ship = part1(&input);
-    println!("Part 1: {}.", ship.x.abs() + ship.y.abs());
-    let ship = part2(&input);
-    println!("Part 2: {}.", ship.pos.x.abs() + ship.pos.y.abs());
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("B4\nH270\nL270"),
+            Ok(vec![
+                Instruction::Forward(-4),
+                Instruction::SetHeading(2),
+                Instruction::Turn(3),
+            ])
+        );
+        assert_eq!(
+            parse("F10\nR45"),
+            Err("Line 2: Angle 45 is not a multiple of 90 degrees.".to_string())
+        );
+        assert_eq!(parse("X1"), Err("Line 1: Unknown action 'X'.".to_string()));
+        assert_eq!(parse("N\n"), Err("Line 1: Invalid value \"\".".to_string()));
+        assert_eq!(
+            parse("F1\n\n"),
+            Err("Line 2: Empty instruction.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extended_commands() -> Result<(), String> {
+        let ship = navigate(&parse("H180\nF5\nB2\nR90")?, Model::Heading);
+        assert_eq!(ship.pos, Point::new(0, -3));
+        assert_eq!(ship.vector, WEST);
+
+        let ship = navigate(&parse("H0\nB1\nH90")?, Model::Waypoint);
+        assert_eq!(ship.pos, Point::new(1, -10));
+        assert_eq!(ship.vector, Point::new(10, 1));
+        assert_eq!(ship.heading, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trace() -> Result<(), String> {
+        let route = trace(&parse("F10\nN3\nF7\nR90\nF11")?, Model::Waypoint);
+        let positions: Vec<_> = route.positions().collect();
+        assert_eq!(
+            positions,
+            vec![
+                Point::new(0, 0),
+                Point::new(100, 10),
+                Point::new(100, 10),
+                Point::new(170, 38),
+                Point::new(170, 38),
+                Point::new(214, -72),
+            ]
+        );
+        assert_eq!(route.ships[4].vector, Point::new(4, -10));
+        assert_eq!(
+            route.bounding_box(),
+            (Point::new(0, -72), Point::new(214, 38))
+        );
+        assert_eq!(route.furthest_point(), Point::new(214, -72));
+
+        let route = trace(&parse("F3\nL90\nF4")?, Model::Heading);
+        assert_eq!(route.total_distance(), 7.0);
+        assert_eq!(
+            route.to_svg(),
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1 -6 5 7\">\n",
+                "  <polyline points=\"0,0 3,0 3,-4\" fill=\"none\" stroke=\"black\" ",
+                "vector-effect=\"non-scaling-stroke\"/>\n",
+                "  <line x1=\"3\" y1=\"-4\" x2=\"3\" y2=\"-5\" stroke=\"red\" ",
+                "vector-effect=\"non-scaling-stroke\"/>\n",
+                "</svg>\n"
+            )
+        );
+
+        Ok(())
+    }
 }
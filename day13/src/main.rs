@@ -1,66 +1,156 @@
+use number_theory::crt;
 use std::fs;
+use std::str::FromStr;
 
-fn main() {
+fn main() -> Result<(), String> {
     let contents = fs::read_to_string("input.txt").expect("Could not read file.");
     let lines: Vec<_> = contents.lines().collect();
-    let ts = lines[0].parse::<u32>().expect("Could not parse timestamp.");
-    let ids = lines[1];
+    let ts = lines[0]
+        .parse::<u64>()
+        .map_err(|_| format!("Could not parse timestamp \"{}\".", lines[0]))?;
+    let schedule = lines[1].parse::<Schedule>()?;
+
+    println!("Part 1: {}.", part1(ts, &schedule)?);
+    println!("Part 2: {}.", part2(&schedule)?);
+
+    let args: Vec<String> = std::env::args().collect();
+    let arg = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .map(|i| args.get(i + 1).map(String::as_str))
+    };
+
+    if let Some(n) = arg("--departures") {
+        let n = n
+            .and_then(|n| n.parse().ok())
+            .ok_or("Expected a count after \"--departures\".")?;
+        for (id, times) in schedule.departures(ts, n) {
+            let times: Vec<_> = times.iter().map(u64::to_string).collect();
+            println!("Bus {}: {}.", id, times.join(", "));
+        }
+    }
 
-    println!("Part 1: {}.", part1(ts, &ids));
-    println!("Part 2: {}.", part2(&ids));
-}
+    if let Some(pattern) = arg("--pattern") {
+        let pattern = pattern
+            .ok_or("Expected a pattern after \"--pattern\".")?
+            .split(',')
+            .map(|pair| {
+                let mut parts = pair.splitn(2, ':');
+                match (parts.next(), parts.next()) {
+                    (Some(id), Some(offset)) => Ok((
+                        id.parse()
+                            .map_err(|_| format!("Invalid bus ID \"{}\".", id))?,
+                        offset
+                            .parse()
+                            .map_err(|_| format!("Invalid offset \"{}\".", offset))?,
+                    )),
+                    _ => Err(format!("Expected \"bus:offset\" but found \"{}\".", pair)),
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        println!("Pattern: {}.", schedule.first_pattern(&pattern)?);
+    }
 
-fn part1(ts: u32, ids: &str) -> u32 {
-    let id = ids
-        .split(',')
-        .filter_map(|s| s.parse().ok())
-        .min_by_key(|x| x - ts % x)
-        .expect("No result found");
+    if let Some(radius) = arg("--timetable") {
+        let radius = radius.and_then(|r| r.parse().ok()).unwrap_or(10);
+        print!(
+            "{}",
+            schedule.timetable(ts.saturating_sub(radius), ts + radius)
+        );
+    }
 
-    id * (id - ts % id)
+    Ok(())
 }
 
-fn crt(pairs: &[(i64, i64)]) -> i64 {
-    #[allow(non_snake_case)]
-    let N: i64 = pairs.iter().map(|x| x.0).product();
-
-    let res = pairs.iter().fold(0, |res, (n_i, a_i)| {
-        let a = n_i;
-        let b = N / n_i;
-        let (_, _, s_i) = extended_euclid(*a, b);
-        res + a_i * s_i * b
-    });
+/// Bus IDs together with their position in the schedule line.
+struct Schedule {
+    buses: Vec<(u64, u64)>,
+}
 
-    res.rem_euclid(N)
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let buses = s
+            .trim()
+            .split(',')
+            .enumerate()
+            .filter(|&(_, id)| id != "x")
+            .map(|(i, id)| match id.parse() {
+                Ok(0) | Err(_) => Err(format!("Invalid bus ID \"{}\" at position {}.", id, i)),
+                Ok(id) => Ok((id, i as u64)),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { buses })
+    }
 }
 
-fn extended_euclid(x: i64, y: i64) -> (i64, i64, i64) {
-    let (mut x, mut x0, mut x1, mut y, mut y0, mut y1) = (x, 1, 0, y, 0, 1);
-
-    while y > 0 {
-        let q = x / y;
-        let y_old = y;
-        y = x % y;
-        x = y_old;
-        let x0_old = x0;
-        x0 = x1;
-        x1 = x0_old - q * x1;
-        let y0_old = y0;
-        y0 = y1;
-        y1 = y0_old - q * y1;
+impl Schedule {
+    /// The next `n` departures of every bus at or after `ts`.
+    fn departures(&self, ts: u64, n: usize) -> Vec<(u64, Vec<u64>)> {
+        self.buses
+            .iter()
+            .map(|&(id, _)| {
+                let first = ts.div_ceil(id) * id;
+                (id, (0..n as u64).map(|k| first + k * id).collect())
+            })
+            .collect()
+    }
+
+    /// First timestamp `t` at which each bus `id` departs at `t + offset`.
+    fn first_pattern(&self, pattern: &[(u64, u64)]) -> Result<i128, String> {
+        let pairs = pattern
+            .iter()
+            .map(|&(id, offset)| {
+                if self.buses.iter().any(|&(bus, _)| bus == id) {
+                    Ok((i128::from(id), -i128::from(offset)))
+                } else {
+                    Err(format!("Bus {} is not in the schedule.", id))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(crt(&pairs)?.0)
     }
 
-    (x, x0, y0)
+    /// Renders departures between `start` and `end` inclusive, marking each
+    /// departure with `D` as in the puzzle description.
+    fn timetable(&self, start: u64, end: u64) -> String {
+        let width = end.to_string().len().max("time".len());
+        let mut out = format!("{:<width$}", "time", width = width);
+
+        for &(id, _) in &self.buses {
+            out += &format!("  {:^8}", format!("bus {}", id));
+        }
+        out = out.trim_end().to_string() + "\n";
+
+        for ts in start..=end {
+            let mut row = format!("{:<width$}", ts, width = width);
+            for &(id, _) in &self.buses {
+                row += &format!("  {:^8}", if ts % id == 0 { "D" } else { "." });
+            }
+            out += row.trim_end();
+            out += "\n";
+        }
+
+        out
+    }
 }
 
-fn part2(ids: &str) -> i64 {
-    let pairs: Vec<_> = ids
-        .split(',')
-        .enumerate()
-        .filter_map(|(i, s)| s.parse().ok().map(|x| (x, x - i as i64)))
-        .collect();
+fn part1(ts: u64, schedule: &Schedule) -> Result<u64, String> {
+    let (id, departure) = schedule
+        .departures(ts, 1)
+        .into_iter()
+        .map(|(id, times)| (id, times[0]))
+        .min_by_key(|&(_, departure)| departure)
+        .ok_or("No buses in the schedule.")?;
+
+    Ok(id * (departure - ts))
+}
 
-    crt(&pairs)
+fn part2(schedule: &Schedule) -> Result<i128, String> {
+    schedule.first_pattern(&schedule.buses)
 }
 
 #[cfg(test)]
@@ -68,26 +158,63 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_part1() {
-        assert_eq!(part1(939, "7,13,x,x,59,x,31,19"), 295);
+    fn test_part1() -> Result<(), String> {
+        assert_eq!(part1(939, &"7,13,x,x,59,x,31,19".parse()?), Ok(295));
+        assert_eq!(
+            part1(939, &"x,x".parse()?),
+            Err("No buses in the schedule.".to_string())
+        );
+
+        Ok(())
     }
 
     #[test]
-    fn test_extended_euclid() {
-        assert_eq!(extended_euclid(102, 38), (2, 3, -8));
+    fn test_part2() -> Result<(), String> {
+        assert_eq!(part2(&"7,13,x,x,59,x,31,19".parse()?), Ok(1068781));
+
+        Ok(())
     }
 
     #[test]
-    fn test_crt() {
-        assert_eq!(crt(&[(3, 0), (4, 3), (5, 4)]), 39);
+    fn test_schedule() -> Result<(), String> {
+        let schedule = "7,13,x,x,59,x,31,19".parse::<Schedule>()?;
+        assert_eq!(
+            schedule.departures(939, 2),
+            vec![
+                (7, vec![945, 952]),
+                (13, vec![949, 962]),
+                (59, vec![944, 1003]),
+                (31, vec![961, 992]),
+                (19, vec![950, 969]),
+            ]
+        );
+        assert_eq!(schedule.first_pattern(&[(7, 0), (13, 1)]), Ok(77));
+        assert_eq!(schedule.first_pattern(&[(13, 0), (7, 1)]), Ok(13));
         assert_eq!(
-            crt(&[(7, 0), (13, 12), (59, 55), (31, 25), (19, 12)]),
-            1068781
+            schedule.first_pattern(&[(5, 0)]),
+            Err("Bus 5 is not in the schedule.".to_string())
         );
+        assert_eq!(
+            "7,x,q".parse::<Schedule>().err(),
+            Some("Invalid bus ID \"q\" at position 2.".to_string())
+        );
+
+        Ok(())
     }
 
     #[test]
-    fn test_part2() {
-        assert_eq!(part2("7,13,x,x,59,x,31,19"), 1068781);
+    fn test_timetable() -> Result<(), String> {
+        let schedule = "7,13,x,x,59,x,31,19".parse::<Schedule>()?;
+        assert_eq!(
+            schedule.timetable(943, 945),
+            concat!(
+                "time   bus 7     bus 13    bus 59    bus 31    bus 19\n",
+                "943      .         .         .         .         .\n",
+                "944      .         .         D         .         .\n",
+                "945      D         .         .         .         .\n",
+            )
+        );
+
+        Ok(())
     }
 }
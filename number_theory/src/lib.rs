@@ -0,0 +1,432 @@
+//! Number-theory primitives shared by the puzzle solutions.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::ops::{Add, Div, Mul, Rem, Sub};
+
+/// Signed integer types the number-theory routines work over. Signedness is
+/// needed for the Bézout coefficients of the extended Euclidean algorithm.
+pub trait Integer:
+    Copy
+    + Ord
+    + Hash
+    + Display
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn checked_neg(self) -> Option<Self>;
+    fn rem_euclid(self, other: Self) -> Self;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Integer for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+
+                fn checked_mul(self, other: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, other)
+                }
+
+                fn checked_neg(self) -> Option<Self> {
+                    <$t>::checked_neg(self)
+                }
+
+                fn rem_euclid(self, other: Self) -> Self {
+                    <$t>::rem_euclid(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, i128, isize);
+
+/// Returns `(g, s, t)` with `g = gcd(x, y)` and `s * x + t * y = g`.
+pub fn extended_euclid<T: Integer>(x: T, y: T) -> (T, T, T) {
+    let (mut x, mut x0, mut x1, mut y, mut y0, mut y1) = (x, T::ONE, T::ZERO, y, T::ZERO, T::ONE);
+
+    while y != T::ZERO {
+        let q = x / y;
+        let y_old = y;
+        y = x % y;
+        x = y_old;
+        let x0_old = x0;
+        x0 = x1;
+        x1 = x0_old - q * x1;
+        let y0_old = y0;
+        y0 = y1;
+        y1 = y0_old - q * y1;
+    }
+
+    if x < T::ZERO {
+        (T::ZERO - x, T::ZERO - x0, T::ZERO - y0)
+    } else {
+        (x, x0, y0)
+    }
+}
+
+/// Non-negative greatest common divisor. Panics if the result does not fit
+/// in `T`, which only happens for `gcd(T::MIN, 0)` and `gcd(T::MIN, T::MIN)`.
+pub fn gcd<T: Integer>(a: T, b: T) -> T {
+    let (mut a, mut b) = (a, b);
+
+    while b != T::ZERO {
+        // `T::MIN % -1` overflows even though the remainder is zero.
+        let r = if b == T::ONE || b == T::ZERO - T::ONE {
+            T::ZERO
+        } else {
+            a % b
+        };
+        a = b;
+        b = r;
+    }
+
+    if a < T::ZERO {
+        T::ZERO - a
+    } else {
+        a
+    }
+}
+
+/// Least common multiple, or `None` if it does not fit in `T`.
+pub fn lcm<T: Integer>(a: T, b: T) -> Option<T> {
+    if a == T::ZERO || b == T::ZERO {
+        return Some(T::ZERO);
+    }
+
+    let l = (a / gcd(a, b)).checked_mul(b)?;
+    if l < T::ZERO {
+        l.checked_neg()
+    } else {
+        Some(l)
+    }
+}
+
+/// Inverse of `a` modulo `m`, if `m` is positive and `a` and `m` are coprime.
+pub fn mod_inverse<T: Integer>(a: T, m: T) -> Option<T> {
+    if m <= T::ZERO {
+        return None;
+    }
+
+    let (g, s, _) = extended_euclid(a.rem_euclid(m), m);
+
+    if g == T::ONE {
+        Some(s.rem_euclid(m))
+    } else {
+        None
+    }
+}
+
+fn mod_add<T: Integer>(a: T, b: T, m: T) -> T {
+    if a >= m - b {
+        a - (m - b)
+    } else {
+        a + b
+    }
+}
+
+/// `a * b mod m` for `a, b` in `0..m`, without overflowing even when the
+/// product does not fit in `T`. Panics if `m` is not positive.
+pub fn mod_mul<T: Integer>(a: T, b: T, m: T) -> T {
+    if let Some(p) = a.checked_mul(b) {
+        return p.rem_euclid(m);
+    }
+
+    let two = T::ONE + T::ONE;
+    let (mut a, mut b, mut res) = (a, b, T::ZERO);
+
+    while b > T::ZERO {
+        if b % two == T::ONE {
+            res = mod_add(res, a, m);
+        }
+        a = mod_add(a, a, m);
+        b = b / two;
+    }
+
+    res
+}
+
+/// `base ^ exp mod m` for non-negative `exp`. Panics if `m` is not positive.
+pub fn mod_pow<T: Integer>(base: T, exp: T, m: T) -> T {
+    let two = T::ONE + T::ONE;
+    let (mut base, mut exp, mut res) = (base.rem_euclid(m), exp, T::ONE.rem_euclid(m));
+
+    while exp > T::ZERO {
+        if exp % two == T::ONE {
+            res = mod_mul(res, base, m);
+        }
+        base = mod_mul(base, base, m);
+        exp = exp / two;
+    }
+
+    res
+}
+
+/// Smallest non-negative `x` with `base ^ x ≡ target (mod m)`, found with
+/// baby-step giant-step. `m` must be positive and coprime to `base`.
+pub fn discrete_log<T: Integer>(base: T, target: T, m: T) -> Option<T> {
+    if m <= T::ZERO {
+        return None;
+    }
+
+    let target = target.rem_euclid(m);
+    let mut n = T::ONE;
+    while n.checked_mul(n).is_some_and(|sq| sq < m) {
+        n = n + T::ONE;
+    }
+
+    let mut baby_steps = HashMap::new();
+    let mut value = T::ONE.rem_euclid(m);
+    let mut j = T::ZERO;
+    while j < n {
+        baby_steps.entry(value).or_insert(j);
+        value = mod_mul(value, base.rem_euclid(m), m);
+        j = j + T::ONE;
+    }
+
+    let giant_step = mod_pow(mod_inverse(base, m)?, n, m);
+    let mut value = target;
+    let mut i = T::ZERO;
+    while i < n {
+        if let Some(&j) = baby_steps.get(&value) {
+            return Some(i * n + j);
+        }
+        value = mod_mul(value, giant_step, m);
+        i = i + T::ONE;
+    }
+
+    None
+}
+
+/// Solves the system `x ≡ a_i (mod n_i)` for pairs `(n_i, a_i)`, returning the
+/// smallest non-negative solution together with the period of all solutions.
+/// The moduli must be positive but need not be coprime.
+pub fn crt<T: Integer>(pairs: &[(T, T)]) -> Result<(T, T), String> {
+    pairs
+        .iter()
+        .try_fold((T::ZERO, T::ONE), |(x, period), &(n, a)| {
+            if n <= T::ZERO {
+                return Err(format!("Invalid modulus {}.", n));
+            }
+
+            let a = a.rem_euclid(n);
+            let (g, p, _) = extended_euclid(period, n);
+            let diff = a - x;
+
+            if diff % g != T::ZERO {
+                return Err(format!(
+                    "Inconsistent congruences: x ≡ {} (mod {}) and x ≡ {} (mod {}).",
+                    x, period, a, n
+                ));
+            }
+
+            // `p * period ≡ g (mod n)`, so stepping `x` by `period` closes the gap in `k` steps.
+            let m = n / g;
+            let k = mod_mul((diff / g).rem_euclid(m), p.rem_euclid(m), m);
+            let lcm = period
+                .checked_mul(m)
+                .ok_or("Overflow while combining congruences.")?;
+
+            Ok(((x + period * k).rem_euclid(lcm), lcm))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic xorshift generator for randomized property checks.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: i64) -> i64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as i64
+        }
+    }
+
+    #[test]
+    fn test_extended_euclid() {
+        assert_eq!(extended_euclid(102, 38), (2, 3, -8));
+        assert_eq!(extended_euclid(-4i8, 6), (2, 1, 1));
+
+        for x in -60i16..60 {
+            for y in -60i16..60 {
+                let (g, s, t) = extended_euclid(x, y);
+                assert_eq!(s * x + t * y, g);
+                assert!(g >= 0);
+                assert_eq!(gcd(x, y), g);
+                if g != 0 {
+                    assert_eq!((x % g, y % g), (0, 0));
+                    assert_eq!(gcd(x / g, y / g), 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lcm() {
+        assert_eq!(lcm(4, 6), Some(12));
+        assert_eq!(lcm(-4, 6), Some(12));
+        assert_eq!(lcm(0, 6), Some(0));
+        assert_eq!(lcm(100i8, 3), None);
+        assert_eq!(lcm(i64::MAX, 1), Some(i64::MAX));
+        assert_eq!(lcm(i64::MIN, 1), None);
+        assert_eq!(lcm(i64::MIN, -1), None);
+        assert_eq!(lcm(i64::MIN, 2), None);
+        assert_eq!(lcm(i64::MIN + 1, 1), Some(i64::MAX));
+
+        for a in 1i32..60 {
+            for b in 1i32..60 {
+                assert_eq!(lcm(a, b).map(|l| l * gcd(a, b)), Some(a * b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_mod_inverse() {
+        assert_eq!(mod_inverse(3, 7), Some(5));
+        assert_eq!(mod_inverse(-3, 7), Some(2));
+        assert_eq!(mod_inverse(4, 6), None);
+        assert_eq!(mod_inverse(3, 0), None);
+        assert_eq!(mod_inverse(3, -7), None);
+
+        for m in 1i16..80 {
+            for a in 0..m {
+                match mod_inverse(a, m) {
+                    Some(inv) => assert_eq!(a * inv % m, 1 % m),
+                    None => assert_ne!(gcd(a, m), 1),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_mod_mul_and_pow() {
+        let m = i64::MAX - 24; // A large prime.
+        assert_eq!(mod_mul(m - 1, m - 1, m), 1);
+        assert_eq!(mod_pow(2, m - 1, m), 1);
+        assert_eq!(mod_pow(5i128, 0, 1), 0);
+        assert_eq!(mod_pow(-2, 3, 7), 6);
+
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..1000 {
+            let m = rng.below(i64::MAX - 1) + 1;
+            let (a, b) = (rng.below(m), rng.below(m));
+            let expected = (a as i128 * b as i128 % m as i128) as i64;
+            assert_eq!(mod_mul(a, b, m), expected);
+            assert_eq!(mod_mul(a as i128, b as i128, m as i128), expected as i128);
+        }
+
+        for m in 1i32..40 {
+            for base in 0..m {
+                let mut expected = 1 % m;
+                for exp in 0..20 {
+                    assert_eq!(mod_pow(base, exp, m), expected);
+                    expected = expected * base % m;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_discrete_log() {
+        assert_eq!(discrete_log(7, 20201227, 20201227), None);
+        assert_eq!(discrete_log(7i64, 5764801, 20201227), Some(8));
+        assert_eq!(discrete_log(2, 0, 4), None);
+        assert_eq!(discrete_log(3, 1, 0), None);
+        assert_eq!(discrete_log(3, 1, -7), None);
+
+        for m in 1i32..60 {
+            for base in 1..m {
+                if gcd(base, m) != 1 {
+                    continue;
+                }
+                for target in 0..m {
+                    let expected = (0..m).find(|&x| mod_pow(base, x, m) == target);
+                    assert_eq!(discrete_log(base, target, m), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt(&[(3, 0), (4, 3), (5, 4)]), Ok((39, 60)));
+        assert_eq!(
+            crt(&[(7, 0), (13, 12), (59, 55), (31, 25), (19, 12)]),
+            Ok((1068781, 3162341))
+        );
+        assert_eq!(crt::<i32>(&[]), Ok((0, 1)));
+        assert_eq!(crt(&[(5, -1)]), Ok((4, 5)));
+        assert_eq!(crt(&[(4, 1), (6, 3)]), Ok((9, 12)));
+        assert_eq!(crt(&[(6, 2), (10, 8), (15, 8)]), Ok((8, 30)));
+        assert_eq!(
+            crt(&[(4, 0), (6, 1)]),
+            Err("Inconsistent congruences: x ≡ 0 (mod 4) and x ≡ 1 (mod 6).".to_string())
+        );
+        assert_eq!(crt(&[(0, 1)]), Err("Invalid modulus 0.".to_string()));
+        assert_eq!(
+            crt(&[(-5, 1), (3, 2)]),
+            Err("Invalid modulus -5.".to_string())
+        );
+        assert_eq!(
+            crt(&[(100i8, 1), (3, 1)]),
+            Err("Overflow while combining congruences.".to_string())
+        );
+
+        for n1 in 1i16..13 {
+            for n2 in 1..13 {
+                for a1 in 0..n1 {
+                    for a2 in 0..n2 {
+                        let expected = (0..n1 * n2).find(|x| x % n1 == a1 && x % n2 == a2);
+                        match crt(&[(n1, a1), (n2, a2)]) {
+                            Ok((x, period)) => {
+                                assert_eq!(Some(x), expected);
+                                assert_eq!(Some(period), lcm(n1, n2));
+                            }
+                            Err(_) => assert_eq!(expected, None),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_crt_large() {
+        let primes = [
+            2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79,
+        ];
+        let pairs: Vec<(i128, i128)> = primes.iter().map(|&p| (p, p - 1)).collect();
+        let period: i128 = primes.iter().product();
+        assert!(period > i64::MAX as i128);
+        assert_eq!(crt(&pairs), Ok((period - 1, period)));
+
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..200 {
+            let x = rng.below(i64::MAX) as i128;
+            let pairs: Vec<(i128, i128)> = (0..4)
+                .map(|_| {
+                    let n = rng.below(1 << 20) as i128 + 1;
+                    (n, x % n)
+                })
+                .collect();
+            let (solution, period) = crt(&pairs).expect("Consistent system.");
+            assert!(solution <= x);
+            assert_eq!((x - solution) % period, 0);
+        }
+    }
+}